@@ -1,4 +1,4 @@
-use super::Parser;
+use super::{Parser, SourceLocation};
 use miette::{Diagnostic, NamedSource, SourceSpan};
 use std::error;
 use std::fmt;
@@ -17,25 +17,39 @@ pub struct ParserError {
 
     #[label("This bit here")]
     pub location: SourceSpan,
+
+    // The source the location points into, used to render the code frame
+    source: String,
 }
 
 const ERR_EOF: &'static str = "Unexpected EOF";
 
 impl ParserError {
     pub fn new(p: &Parser, message: impl Into<String>) -> Self {
+        // A source span is made out of a offset and a length
         let location = if p.current_char > 1 {
-            (p.current_char - 2, p.current_char - 1)
+            (p.current_char - 2, 1)
         } else {
             (0, 1)
         };
 
+        let source: String = p.source_chars[..p.source_chars_len].iter().collect();
         Self {
             message: message.into(),
-            src: NamedSource::new("file.vue", p.source_chars.iter().collect::<String>()),
+            src: NamedSource::new("file.vue", source.clone()),
             location: location.into(),
+            source,
         }
     }
 
+    // Creates an error pointing at a specific location instead of the current parser position
+    pub fn new_at(p: &Parser, location: &SourceLocation, message: impl Into<String>) -> Self {
+        let mut err = Self::new(p, message);
+        // A source span is made out of a offset and a length
+        err.location = (location.0, location.1.saturating_sub(location.0).max(1)).into();
+        err
+    }
+
     pub fn eof(p: &Parser) -> Self {
         Self::new(p, ERR_EOF)
     }
@@ -43,6 +57,62 @@ impl ParserError {
     pub fn is_eof(&self) -> bool {
         self.message == ERR_EOF
     }
+
+    // Returns the line (starting at 1) and column (starting at 0) the error points at
+    pub fn line_column(&self) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 0;
+        for c in self.source.chars().take(self.location.offset()) {
+            if c == '\n' {
+                line += 1;
+                column = 0;
+            } else {
+                column += 1;
+            }
+        }
+        (line, column)
+    }
+
+    // Renders the lines around the error with the location underlined, Example:
+    // 1 | <template>
+    // 2 |   <div>{{ foo + }}</div>
+    //   |                 ^
+    // 3 | </template>
+    pub fn code_frame(&self) -> String {
+        const CONTEXT_LINES: usize = 2;
+
+        let (line, column) = self.line_column();
+        let lines: Vec<&str> = self.source.split('\n').collect();
+        let first = line.saturating_sub(CONTEXT_LINES).max(1);
+        let last = (line + CONTEXT_LINES).min(lines.len());
+        let gutter_width = last.to_string().len();
+
+        let mut resp = String::new();
+        for line_number in first..=last {
+            let content = lines[line_number - 1].trim_end_matches('\r');
+            resp.push_str(&format!(
+                "{:>width$} | {}\n",
+                line_number,
+                content,
+                width = gutter_width
+            ));
+            if line_number == line {
+                let underline_len = self
+                    .location
+                    .len()
+                    .min(content.chars().count().saturating_sub(column))
+                    .max(1);
+                resp.push_str(&format!(
+                    "{:>width$} | {}{}\n",
+                    "",
+                    " ".repeat(column),
+                    "^".repeat(underline_len),
+                    width = gutter_width
+                ));
+            }
+        }
+        resp.trim_end_matches('\n').to_string()
+    }
 }
 
 impl error::Error for ParserError {}
@@ -0,0 +1,369 @@
+mod parser;
+mod printer;
+mod script_setup;
+mod tokenizer;
+mod ts_types;
+
+use super::{template, Parser, ParserError, QuoteKind, ScriptSetup, SourceLocation};
+pub use parser::{ParsedJs, Reference, TemplateJs};
+pub use printer::TEMP_VARIABLES;
+pub use script_setup::ScriptSetupInfo;
+
+// The globals that can be used within templates, these are never prefixed with `_vm.`
+// Same as the allowedGlobals of vue
+// https://github.com/vuejs/vue/blob/v2.7.14/src/core/instance/proxy.ts
+const ALLOWED_GLOBALS: [&str; 25] = [
+    "Infinity",
+    "undefined",
+    "NaN",
+    "isFinite",
+    "isNaN",
+    "parseFloat",
+    "parseInt",
+    "decodeURI",
+    "decodeURIComponent",
+    "encodeURI",
+    "encodeURIComponent",
+    "Math",
+    "Number",
+    "Date",
+    "Array",
+    "Object",
+    "Boolean",
+    "String",
+    "RegExp",
+    "Map",
+    "Set",
+    "JSON",
+    "Intl",
+    "BigInt",
+    "require",
+];
+
+fn is_allowed_global(p: &Parser, name: &str) -> bool {
+    ALLOWED_GLOBALS.contains(&name)
+        || p.options
+            .allowed_globals
+            .iter()
+            .any(|global| global == name)
+}
+
+// Compiles the javascript of a template value into javascript that can be used within the render function
+pub fn compile_template_js(
+    p: &Parser,
+    js: &SourceLocation,
+    kind: TemplateJs,
+) -> Result<String, ParserError> {
+    let parsed = parse_template_js(p, js, kind)?;
+    Ok(add_vm_references(p, js, &parsed))
+}
+
+// Compiles a v-on handler into the listener function
+// Function expressions (@click="e => foo(e)") are used as listener, other handlers are wrapped in a function with the event as parameter
+pub fn compile_template_handler(p: &Parser, js: &SourceLocation) -> Result<String, ParserError> {
+    let parsed = parse_template_js(p, js, TemplateJs::Statements)?;
+    let handler = add_vm_references(p, js, &parsed);
+    if parsed.function_handler {
+        Ok(handler)
+    } else {
+        Ok(format!("function($event){{{}}}", handler))
+    }
+}
+
+// Parses the javascript of a template value and returns the references to variables that are not defined within the javascript itself
+pub fn parse_template_js(
+    p: &Parser,
+    js: &SourceLocation,
+    kind: TemplateJs,
+) -> Result<ParsedJs, ParserError> {
+    parser::parse(&p.source_chars, js, kind)
+        .map_err(|err| ParserError::new_at(p, &err.location, err.message))
+}
+
+pub fn add_vm_references(p: &Parser, js: &SourceLocation, parsed: &ParsedJs) -> String {
+    printer::JsPrinter::new(p, parsed, p.options.lower_optional_chaining).print(js)
+}
+
+// Finds the imports, bindings and compiler macros of <script setup>
+pub fn analyze_script_setup(
+    p: &Parser,
+    content: &SourceLocation,
+) -> Result<ScriptSetupInfo, ParserError> {
+    script_setup::analyze(&p.source_chars, content)
+        .map_err(|err| ParserError::new_at(p, &err.location, err.message))
+}
+
+// Compiles <script setup> (and the normal <script> if there is one) into the module that exports the component
+pub fn compile_script_setup(p: &Parser, setup: &ScriptSetup) -> Result<String, ParserError> {
+    script_setup::compile(p, setup)
+}
+
+// Compiles the script(s) of a component into javascript that is placed within the main module and assigns the component to c
+// Returns None if the script has to be imported from a separate module, see script_setup::compile_inline
+pub fn compile_inline_script(
+    p: &Parser,
+    is_reserved: impl Fn(&str) -> bool,
+) -> Result<Option<String>, ParserError> {
+    script_setup::compile_inline(p, is_reserved)
+}
+
+fn is_setup_binding(p: &Parser, name: &str) -> bool {
+    match p.script_setup.as_ref() {
+        Some(setup) => setup.info.has_binding(name),
+        None => false,
+    }
+}
+
+// Returns the setup binding a component tag refers to,
+// <my-component> can refer to MyComponent, myComponent or my-component
+pub fn setup_binding_for_tag(p: &Parser, tag: &str) -> Option<String> {
+    p.script_setup.as_ref()?;
+
+    let mut camel_case = String::new();
+    let mut upper_next = false;
+    for c in tag.chars() {
+        if c == '-' {
+            upper_next = true;
+        } else if upper_next {
+            camel_case.push(c.to_ascii_uppercase());
+            upper_next = false;
+        } else {
+            camel_case.push(c);
+        }
+    }
+    let mut pascal_case = camel_case.clone();
+    if let Some(first) = pascal_case.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+
+    [tag.to_string(), camel_case, pascal_case]
+        .iter()
+        .find(|name| is_setup_binding(p, name))
+        .cloned()
+}
+
+fn write_reference(p: &Parser, reference: &Reference, resp: &mut String) {
+    let name = reference.location.string(p);
+    if reference.shorthand {
+        // { foo } becomes { foo: _vm.foo }
+        resp.push_str(&name);
+        resp.push(':');
+    }
+
+    if name == "this" {
+        resp.push_str("_vm");
+    } else if is_allowed_global(p, &name) || p.local_variables.contains_key(&name) {
+        // is a global or local variable, do not make modifications
+        resp.push_str(&name);
+    } else if is_setup_binding(p, &name) {
+        // Setup bindings are accessed through the setup proxy that unwraps refs
+        resp.push_str("_setup.");
+        resp.push_str(&name);
+    } else {
+        // In functional templates _vm is the render context, its keys (props, listeners, ..) are accessed the same way
+        resp.push_str("_vm.");
+        resp.push_str(&name);
+    }
+}
+
+// parses {{ foo + ' ' + bar }}
+// The closing delimiter is taken from the compiler options, it's only matched outside of strings, comments and brackets
+// so the characters of the delimiter can be used within the expression ({% a % b %})
+// Returns the location of the javascript within the delimiters
+pub fn parse_template_var(p: &mut Parser) -> Result<SourceLocation, ParserError> {
+    let start = p.current_char;
+    let close_delimiter: Vec<char> = p.options.delimiters.1.chars().collect();
+
+    loop {
+        if p.next_is(&close_delimiter) {
+            let end = p.current_char;
+            p.current_char += close_delimiter.len();
+            return Ok(SourceLocation(start, end));
+        }
+
+        let c = p.must_read_one()?;
+        handle_common(p, c, true)?;
+    }
+}
+
+// parses v-bind:value="some_value"
+// Returns the location of the javascript within the quotes
+pub fn parse_template_arg(p: &mut Parser, closure: char) -> Result<SourceLocation, ParserError> {
+    let start = p.current_char;
+    parse_inline(p, closure, false)?;
+    Ok(SourceLocation(start, p.current_char - 1))
+}
+
+pub fn compile_script_content(p: &mut Parser) -> Result<(), ParserError> {
+    loop {
+        match p.must_read_one()? {
+            c if handle_common(p, c, false)? => {}
+
+            // Check if this is the script tag end </script>
+            // Only closing tags are checked as typescript generics (ref<string>()) look like opening tags
+            '<' if p.must_seek_one()? == '/' => match template::parse_tag(p, false) {
+                Err(e) => {
+                    if e.is_eof() {
+                        return Err(e);
+                    }
+                    // Ignore if error is something else
+                }
+                Ok(tag) => {
+                    // Check tag type, it needs to be </script>
+                    if let template::TagType::Close = tag.type_ {
+                        // We expect this type
+                    } else {
+                        return Err(ParserError::new(
+                            p,
+                            format!("expected script closure but got {}", tag.type_.to_string()),
+                        ));
+                    }
+
+                    // Tag needs to be a script tag
+                    if !tag.name.eq(p, &mut "script".chars()) {
+                        return Err(ParserError::new(
+                            p,
+                            format!("expected script closure but got {}", tag.name.string(p)),
+                        ));
+                    }
+
+                    return Ok(());
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+enum ParseInlineReturnReason {
+    Closure,
+    Comma,
+}
+
+// parses one js action
+// var foo = {a: 1, b: 2}
+//           ^^^^^^^^^^^^
+// var foo = () => something_else
+//                 ^^^^^^^^^^^^^^
+// <div v-bind:foo="a_var + another_var" />
+//                  ^^^^^^^^^^^^^^^^^^^
+fn parse_inline(
+    p: &mut Parser,
+    closure: char,
+    return_on_comma: bool,
+) -> Result<ParseInlineReturnReason, ParserError> {
+    loop {
+        let c = p.must_read_one()?;
+        match c {
+            c if c == closure => return Ok(ParseInlineReturnReason::Closure),
+            c if handle_common(p, c, true)? => {}
+            ',' if return_on_comma => return Ok(ParseInlineReturnReason::Comma),
+            _ => {}
+        }
+    }
+}
+
+// parses a block of javascript.
+// This if for example the body of a function:
+// function () { var a = 1; console.log(a) }
+//               ^^^^^^^^^^^^^^^^^^^^^^^^
+// Or the contents of a script tag:
+// <script> var a = 1; console.log(a) </script>
+//          ^^^^^^^^^^^^^^^^^^^^^^^^
+pub fn parse_block_like(p: &mut Parser, closure: char) -> Result<(), ParserError> {
+    loop {
+        match p.must_read_one()? {
+            c if c == closure => return Ok(()),
+            c if handle_common(p, c, false)? => {}
+            _ => {}
+        }
+    }
+}
+
+// parses a js object structure
+// {foo: 1, bar: 'a'}
+fn parse_object(p: &mut Parser) -> Result<(), ParserError> {
+    loop {
+        match p.must_read_one()? {
+            '}' => return Ok(()),
+            c if handle_common(p, c, true)? => {}
+            ':' => match parse_inline(p, '}', true)? {
+                ParseInlineReturnReason::Closure => return Ok(()),
+                ParseInlineReturnReason::Comma => continue,
+            },
+            _ => {}
+        }
+    }
+}
+
+pub fn parse_name(p: &mut Parser) -> Result<(char, SourceLocation), ParserError> {
+    let start = p.current_char - 1;
+
+    loop {
+        match p.must_read_one()? {
+            '_' => {}
+            c if c.is_numeric() || c.is_ascii_lowercase() || c.is_ascii_uppercase() || c > '}' => {}
+            c => {
+                return Ok((c, SourceLocation(start, p.current_char - 1)));
+            }
+        }
+    }
+}
+
+fn handle_common(p: &mut Parser, c: char, is_inline: bool) -> Result<bool, ParserError> {
+    match c {
+        // Parse string
+        '\'' => {
+            p.parse_quotes(QuoteKind::JSSingle)?;
+            Ok(true)
+        }
+        '"' => {
+            p.parse_quotes(QuoteKind::JSDouble)?;
+            Ok(true)
+        }
+        '`' => {
+            p.parse_quotes(QuoteKind::JSBacktick)?;
+            Ok(true)
+        }
+        // Parse comment
+        '/' if parse_comment(p)? => Ok(true),
+        // Parse block like
+        '{' => {
+            if is_inline {
+                parse_object(p)?;
+            } else {
+                parse_block_like(p, '}')?;
+            }
+            Ok(true)
+        }
+        '(' => {
+            parse_inline(p, ')', false)?;
+            Ok(true)
+        }
+        '[' => {
+            parse_inline(p, ']', false)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn parse_comment(p: &mut Parser) -> Result<bool, ParserError> {
+    match p.must_seek_one()? {
+        '/' => {
+            // this line is a comment
+            p.current_char += 1;
+            p.look_for(vec!['\n'])?;
+            p.current_char -= 1;
+            Ok(true)
+        }
+        '*' => {
+            // look for end of comment
+            p.current_char += 1;
+            p.look_for(vec!['*', '/'])?;
+            p.current_char -= 1;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,1949 @@
+use super::super::SourceLocation;
+use super::tokenizer::{JsError, Token, TokenKind, Tokenizer};
+
+/*
+    A recursive descent parser for the javascript inside of templates
+
+    The parser validates the javascript and collects the references to variables that are not
+    defined within the javascript itself, these are the ones that need to be prefixed with `_vm.`.
+    Variables are resolved after parsing so declarations that are hoisted (var, function) are found.
+
+    Destructuring patterns and arrow function parameters are first parsed as expressions
+    (`({ a, b = 1 }) => a` starts out as a parenthesized object literal),
+    `ExprInfo` describes what an already parsed expression can be converted into.
+*/
+
+// The kind of javascript expected within a template value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateJs {
+    // {{ foo }}, v-bind:foo="foo", v-if="foo"
+    Expression,
+    // v-model="foo.bar"
+    AssignmentTarget,
+    // v-on:click="foo = bar; baz()"
+    Statements,
+    // v-slot:item="{ item, index }"
+    Params,
+}
+
+// A variable that is not defined within the parsed javascript
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub location: SourceLocation,
+    // The reference is a shorthand property ({ foo })
+    pub shorthand: bool,
+}
+
+// Syntax that can be rewritten into ES5 compatible javascript
+#[derive(Debug, Clone)]
+pub enum Rewrite {
+    // left ?? right
+    Nullish {
+        left: SourceLocation,
+        right: SourceLocation,
+    },
+    // object?.rest, the rest goes up to the next ?. or the end of the chain
+    OptionalChain {
+        object: SourceLocation,
+        rest: SourceLocation,
+        kind: OptionalKind,
+        // The start of the delete operator in front of the chain (delete foo?.bar)
+        delete_start: Option<usize>,
+    },
+}
+
+impl Rewrite {
+    pub fn location(&self) -> SourceLocation {
+        match self {
+            Self::Nullish { left, right } => SourceLocation(left.0, right.1),
+            Self::OptionalChain {
+                object,
+                rest,
+                delete_start,
+                ..
+            } => SourceLocation(delete_start.unwrap_or(object.0), rest.1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum OptionalKind {
+    // foo?.bar
+    Member,
+    // foo?.[bar]
+    Computed,
+    // foo?.(bar)
+    Call {
+        // The object of the called method (foo in foo.bar?.()), this is used as `this` for the call
+        this_object: Option<SourceLocation>,
+        has_arguments: bool,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedJs {
+    // The references in source order
+    pub references: Vec<Reference>,
+    pub rewrites: Vec<Rewrite>,
+    // The v-on handler is a single function expression (function (e) { .. } or e => ..)
+    // Vue uses these as the listener instead of wrapping them in a function
+    pub function_handler: bool,
+}
+
+pub fn parse(
+    chars: &[char],
+    location: &SourceLocation,
+    kind: TemplateJs,
+) -> Result<ParsedJs, JsError> {
+    let mut parser = JsParser::new(chars, location)?;
+    let mut function_handler = false;
+
+    match kind {
+        TemplateJs::Expression => {
+            parser.expect_expression_start()?;
+            parser.parse_expression(false)?;
+        }
+        TemplateJs::AssignmentTarget => {
+            parser.expect_expression_start()?;
+            let start = parser.token.start;
+            let info = parser.parse_expression(false)?;
+            if !matches!(info.cover, Cover::Identifier | Cover::Member) {
+                return Err(JsError::new(
+                    SourceLocation(start, parser.prev_token_end),
+                    "expected a variable or property to assign to",
+                ));
+            }
+        }
+        TemplateJs::Statements => {
+            // v-on handlers are compiled into a function with the event as parameter
+            parser.scopes[0].names.push(String::from("$event"));
+            parser.scopes[0].names.push(String::from("arguments"));
+
+            // A function keyword at the start of a statement is a declaration, to allow function expressions
+            // as handler the start of the handler is parsed as expression if it might be a function expression
+            if parser.might_be_function_expression()? {
+                let info = parser.parse_expression(false)?;
+                parser.consume_semicolon()?;
+                function_handler =
+                    info.cover == Cover::Function && parser.token.kind == TokenKind::Eof;
+            }
+            while parser.token.kind != TokenKind::Eof {
+                parser.parse_statement()?;
+            }
+        }
+        TemplateJs::Params => {
+            while parser.token.kind != TokenKind::Eof {
+                if parser.eat("...")? {
+                    parser.parse_binding_target()?;
+                    break;
+                }
+                parser.parse_binding_element()?;
+                if parser.token.kind != TokenKind::Eof {
+                    parser.expect(",")?;
+                }
+            }
+        }
+    }
+
+    if parser.token.kind != TokenKind::Eof {
+        return Err(parser.unexpected());
+    }
+    Ok(ParsedJs {
+        references: parser.unresolved_references(),
+        rewrites: parser.rewrites,
+        function_handler,
+    })
+}
+
+// Words that can never be used as a variable name
+const RESERVED_WORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=",
+    "??=",
+];
+
+// What an expression can be converted into
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cover {
+    // Can only be used as an expression
+    Invalid,
+    // foo, can be assigned to and used as a variable name
+    Identifier,
+    // foo.bar or foo[bar], can be assigned to
+    Member,
+    // An object or array literal that can be converted into a destructuring pattern
+    // binding is true if the pattern only contains variable names (and thus can be used as function parameters)
+    Pattern { binding: bool },
+    // foo = bar, a pattern element with a default value
+    WithDefault { binding: bool },
+    // function () { .. } or () => .., can only be used as an expression
+    Function,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ExprInfo {
+    cover: Cover,
+    // The location of a shorthand property initializer ({ a = 1 })
+    // This is only valid if the expression is converted into a pattern
+    cover_init: Option<usize>,
+}
+
+impl ExprInfo {
+    fn plain() -> Self {
+        Self {
+            cover: Cover::Invalid,
+            cover_init: None,
+        }
+    }
+
+    fn cover(cover: Cover) -> Self {
+        Self {
+            cover,
+            cover_init: None,
+        }
+    }
+
+    fn is_binding(&self) -> bool {
+        matches!(
+            self.cover,
+            Cover::Identifier
+                | Cover::Pattern { binding: true }
+                | Cover::WithDefault { binding: true }
+        )
+    }
+
+    fn is_pattern_element(&self) -> bool {
+        !matches!(self.cover, Cover::Invalid | Cover::Function)
+    }
+}
+
+// Keeps track of the elements of an array or object literal that might become a pattern
+struct PatternState {
+    pattern: bool,
+    binding: bool,
+    cover_init: Option<usize>,
+}
+
+impl PatternState {
+    fn new() -> Self {
+        Self {
+            pattern: true,
+            binding: true,
+            cover_init: None,
+        }
+    }
+
+    fn add(&mut self, info: &ExprInfo) {
+        self.pattern &= info.is_pattern_element();
+        self.binding &= info.is_binding();
+        if self.cover_init.is_none() {
+            self.cover_init = info.cover_init;
+        }
+    }
+
+    // allow_pattern is true for array and parameter rest elements ([...[a, b]] = c)
+    fn add_rest(&mut self, info: &ExprInfo, is_last: bool, allow_pattern: bool) {
+        let valid_target = match info.cover {
+            Cover::Identifier | Cover::Member => true,
+            Cover::Pattern { .. } => allow_pattern,
+            _ => false,
+        };
+        self.pattern &= is_last && valid_target;
+        self.binding &= is_last && valid_target && info.is_binding();
+        if self.cover_init.is_none() {
+            self.cover_init = info.cover_init;
+        }
+    }
+
+    fn add_invalid(&mut self) {
+        self.pattern = false;
+        self.binding = false;
+    }
+
+    fn into_info(self) -> Result<ExprInfo, JsError> {
+        if !self.pattern {
+            if let Some(cover_init) = self.cover_init {
+                return Err(invalid_shorthand_init_err(cover_init));
+            }
+            return Ok(ExprInfo::plain());
+        }
+        Ok(ExprInfo {
+            cover: Cover::Pattern {
+                binding: self.binding,
+            },
+            cover_init: self.cover_init,
+        })
+    }
+}
+
+fn invalid_shorthand_init_err(location: usize) -> JsError {
+    JsError::new(
+        SourceLocation(location, location + 1),
+        "invalid shorthand property initializer",
+    )
+}
+
+// The parser state that is specific to the function being parsed
+struct FunctionContext {
+    in_async: bool,
+    in_generator: bool,
+    in_function: bool,
+    declaring_var: bool,
+}
+
+struct Scope {
+    parent: Option<usize>,
+    // Function scopes contain the var and function declarations
+    is_function: bool,
+    names: Vec<String>,
+}
+
+struct JsParser<'a> {
+    tokenizer: Tokenizer<'a>,
+    token: Token,
+    prev_token_end: usize,
+    in_async: bool,
+    in_generator: bool,
+    // Inside a non arrow function `this` and `arguments` do not refer to the component
+    in_function: bool,
+    // The bindings currently being parsed are declared using var
+    declaring_var: bool,
+    scopes: Vec<Scope>,
+    scope: usize,
+    // The references together with their name and the scope they are used in
+    references: Vec<(Reference, String, usize)>,
+    rewrites: Vec<Rewrite>,
+}
+
+impl<'a> JsParser<'a> {
+    fn new(chars: &'a [char], location: &SourceLocation) -> Result<Self, JsError> {
+        let mut tokenizer = Tokenizer::new(chars, location);
+        let token = tokenizer.next_token()?;
+        Ok(Self {
+            tokenizer,
+            token,
+            prev_token_end: location.0,
+            in_async: false,
+            in_generator: false,
+            in_function: false,
+            declaring_var: false,
+            scopes: vec![Scope {
+                parent: None,
+                is_function: true,
+                names: Vec::new(),
+            }],
+            scope: 0,
+            references: Vec::new(),
+            rewrites: Vec::new(),
+        })
+    }
+
+    //
+    // Scopes
+    //
+
+    fn push_scope(&mut self, is_function: bool) {
+        self.scopes.push(Scope {
+            parent: Some(self.scope),
+            is_function,
+            names: Vec::new(),
+        });
+        self.scope = self.scopes.len() - 1;
+    }
+
+    fn pop_scope(&mut self) {
+        if let Some(parent) = self.scopes[self.scope].parent {
+            self.scope = parent;
+        }
+    }
+
+    fn token_string(&self, token: &Token) -> String {
+        self.tokenizer.text(token).iter().collect()
+    }
+
+    // Adds a variable to the current scope, var declarations are added to the nearest function scope
+    fn declare(&mut self, token: &Token) {
+        let mut scope = self.scope;
+        if self.declaring_var {
+            while !self.scopes[scope].is_function {
+                scope = self.scopes[scope].parent.unwrap_or(0);
+            }
+        }
+        let name = self.token_string(token);
+        self.scopes[scope].names.push(name);
+    }
+
+    fn add_reference(&mut self, token: &Token, shorthand: bool) {
+        if self.in_function && (self.token_is(token, "this") || self.token_is(token, "arguments")) {
+            // Refers to the function itself
+            return;
+        }
+        self.references.push((
+            Reference {
+                location: token.location(),
+                shorthand,
+            },
+            self.token_string(token),
+            self.scope,
+        ));
+    }
+
+    // Returns the references that do not resolve to a variable declared within the javascript
+    fn unresolved_references(&self) -> Vec<Reference> {
+        let mut resp: Vec<Reference> = self
+            .references
+            .iter()
+            .filter(|(_, name, scope)| {
+                let mut scope = Some(*scope);
+                while let Some(current) = scope {
+                    if self.scopes[current].names.contains(name) {
+                        return false;
+                    }
+                    scope = self.scopes[current].parent;
+                }
+                true
+            })
+            .map(|(reference, _, _)| reference.clone())
+            .collect();
+        resp.sort_by_key(|reference| reference.location.0);
+        resp
+    }
+
+    //
+    // Token helpers
+    //
+
+    // Moves to the next token and returns the current token
+    fn next(&mut self) -> Result<Token, JsError> {
+        let current = self.token;
+        self.prev_token_end = current.end;
+        self.token = self.tokenizer.next_token()?;
+        Ok(current)
+    }
+
+    fn peek(&mut self) -> Result<Token, JsError> {
+        let pos = self.tokenizer.pos();
+        let token = self.tokenizer.next_token();
+        self.tokenizer.set_pos(pos);
+        token
+    }
+
+    fn token_is(&self, token: &Token, value: &str) -> bool {
+        match token.kind {
+            TokenKind::Name | TokenKind::Punctuator => {
+                let text = self.tokenizer.text(token);
+                text.len() == value.len() && text.iter().zip(value.chars()).all(|(a, b)| *a == b)
+            }
+            _ => false,
+        }
+    }
+
+    fn is(&self, value: &str) -> bool {
+        self.token_is(&self.token, value)
+    }
+
+    fn is_one_of(&self, values: &[&str]) -> bool {
+        values.iter().any(|value| self.is(value))
+    }
+
+    fn eat(&mut self, value: &str) -> Result<bool, JsError> {
+        if self.is(value) {
+            self.next()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, value: &str) -> Result<Token, JsError> {
+        if self.is(value) {
+            self.next()
+        } else {
+            Err(JsError::new(
+                self.token.location(),
+                format!("expected '{}' but got {}", value, self.describe_token()),
+            ))
+        }
+    }
+
+    fn describe_token(&self) -> String {
+        if self.token.kind == TokenKind::Eof {
+            String::from("the end of the expression")
+        } else {
+            format!(
+                "'{}'",
+                self.tokenizer.text(&self.token).iter().collect::<String>()
+            )
+        }
+    }
+
+    fn unexpected(&self) -> JsError {
+        JsError::new(
+            self.token.location(),
+            format!("unexpected {}", self.describe_token()),
+        )
+    }
+
+    fn expect_expression_start(&self) -> Result<(), JsError> {
+        if self.token.kind == TokenKind::Eof {
+            Err(JsError::new(
+                self.token.location(),
+                "expected an expression",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_reserved_word(&self, token: &Token) -> bool {
+        RESERVED_WORDS.iter().any(|word| self.token_is(token, word))
+            || (self.in_generator && self.token_is(token, "yield"))
+            || (self.in_async && self.token_is(token, "await"))
+    }
+
+    // Returns true if the current token can be used as a variable name
+    fn is_identifier(&self) -> bool {
+        self.token.kind == TokenKind::Name && !self.is_reserved_word(&self.token)
+    }
+
+    // Handles automatic semicolon insertion
+    fn consume_semicolon(&mut self) -> Result<(), JsError> {
+        if self.eat(";")? || self.is("}") || self.token.kind == TokenKind::Eof {
+            return Ok(());
+        }
+        if self.token.newline_before {
+            return Ok(());
+        }
+        Err(self.unexpected())
+    }
+
+    // Returns true if the current token might be the start of a function expression
+    // (function () { .. }, async () => .., (a, b) => .. or a => ..)
+    fn might_be_function_expression(&mut self) -> Result<bool, JsError> {
+        if self.is("function") || self.is("async") || self.is("(") {
+            return Ok(true);
+        }
+        if !self.is_identifier() {
+            return Ok(false);
+        }
+        let peek = self.peek()?;
+        Ok(self.token_is(&peek, "=>"))
+    }
+
+    // Errors if the expression contains syntax that is only valid within a pattern
+    fn expression_only(&self, info: &ExprInfo) -> Result<(), JsError> {
+        match info.cover_init {
+            Some(location) => Err(invalid_shorthand_init_err(location)),
+            None => Ok(()),
+        }
+    }
+
+    //
+    // Statements
+    //
+
+    fn parse_statement(&mut self) -> Result<(), JsError> {
+        if self.token.kind == TokenKind::Punctuator {
+            if self.is("{") {
+                return self.parse_block();
+            }
+            if self.eat(";")? {
+                return Ok(());
+            }
+        }
+
+        if self.token.kind == TokenKind::Name {
+            let peek = self.peek()?;
+            let peek_is_declaration_target = peek.kind == TokenKind::Name
+                || self.token_is(&peek, "[")
+                || self.token_is(&peek, "{");
+
+            if self.is("var") || self.is("const") || (self.is("let") && peek_is_declaration_target)
+            {
+                self.parse_variable_declaration(false)?;
+                return self.consume_semicolon();
+            }
+            if self.is("function") {
+                return self.parse_function(false, true);
+            }
+            if self.is("async") && self.token_is(&peek, "function") && !peek.newline_before {
+                self.next()?;
+                return self.parse_function(true, true);
+            }
+            if self.is("class") {
+                return self.parse_class(true);
+            }
+            if self.eat("if")? {
+                self.parse_paren_expression()?;
+                self.parse_statement()?;
+                if self.eat("else")? {
+                    self.parse_statement()?;
+                }
+                return Ok(());
+            }
+            if self.is("for") {
+                return self.parse_for();
+            }
+            if self.eat("while")? || self.eat("with")? {
+                self.parse_paren_expression()?;
+                return self.parse_statement();
+            }
+            if self.eat("do")? {
+                self.parse_statement()?;
+                self.expect("while")?;
+                self.parse_paren_expression()?;
+                // A semicolon is always inserted after a do while statement
+                self.eat(";")?;
+                return Ok(());
+            }
+            if self.eat("continue")? || self.eat("break")? {
+                if self.is_identifier() && !self.token.newline_before {
+                    self.next()?;
+                }
+                return self.consume_semicolon();
+            }
+            if self.eat("return")? {
+                let has_argument = !self.is(";")
+                    && !self.is("}")
+                    && self.token.kind != TokenKind::Eof
+                    && !self.token.newline_before;
+                if has_argument {
+                    self.parse_expression(false)?;
+                }
+                return self.consume_semicolon();
+            }
+            if self.eat("throw")? {
+                if self.token.newline_before {
+                    return Err(JsError::new(
+                        self.token.location(),
+                        "illegal newline after throw",
+                    ));
+                }
+                self.parse_expression(false)?;
+                return self.consume_semicolon();
+            }
+            if self.is("try") {
+                return self.parse_try();
+            }
+            if self.is("switch") {
+                return self.parse_switch();
+            }
+            if self.eat("debugger")? {
+                return self.consume_semicolon();
+            }
+            if (self.is("import") || self.is("export"))
+                && !self.token_is(&peek, "(")
+                && !self.token_is(&peek, ".")
+            {
+                return Err(JsError::new(
+                    self.token.location(),
+                    "import and export statements are not allowed here",
+                ));
+            }
+            if self.is_identifier() && self.token_is(&peek, ":") {
+                // A labeled statement (foo: for (..) {})
+                self.next()?;
+                self.next()?;
+                return self.parse_statement();
+            }
+        }
+
+        self.parse_expression(false)?;
+        self.consume_semicolon()
+    }
+
+    fn parse_block(&mut self) -> Result<(), JsError> {
+        self.expect("{")?;
+        self.push_scope(false);
+        while !self.eat("}")? {
+            self.parse_statement()?;
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn parse_paren_expression(&mut self) -> Result<(), JsError> {
+        self.expect("(")?;
+        self.parse_expression(false)?;
+        self.expect(")")?;
+        Ok(())
+    }
+
+    // Parses var, let and const declarations, returns the amount of declared bindings
+    fn parse_variable_declaration(&mut self, no_in: bool) -> Result<usize, JsError> {
+        let is_var = self.is("var");
+        self.next()?;
+        let mut count = 0;
+        loop {
+            let outer_declaring_var = self.declaring_var;
+            self.declaring_var = is_var;
+            self.parse_binding_target()?;
+            self.declaring_var = outer_declaring_var;
+            if self.eat("=")? {
+                self.parse_assignment(no_in)?;
+            }
+            count += 1;
+            if !self.eat(",")? {
+                return Ok(count);
+            }
+        }
+    }
+
+    fn parse_for(&mut self) -> Result<(), JsError> {
+        self.expect("for")?;
+        if self.in_async {
+            self.eat("await")?;
+        }
+        self.expect("(")?;
+
+        // Variables declared using let and const in the head of the loop are only visible within the loop
+        self.push_scope(false);
+        self.parse_for_head_and_body()?;
+        self.pop_scope();
+        Ok(())
+    }
+
+    // Parses a for loop after the opening parenthesis
+    fn parse_for_head_and_body(&mut self) -> Result<(), JsError> {
+        if !self.is(";") {
+            let peek = self.peek()?;
+            let is_declaration = self.is("var")
+                || self.is("const")
+                || (self.is("let")
+                    && (peek.kind == TokenKind::Name
+                        || self.token_is(&peek, "[")
+                        || self.token_is(&peek, "{")));
+
+            if is_declaration {
+                let declarations = self.parse_variable_declaration(true)?;
+                if declarations == 1 && (self.is("of") || self.is("in")) {
+                    return self.parse_for_in_or_of_rest();
+                }
+            } else {
+                let start = self.token.start;
+                let init = self.parse_assignment_cover(true)?;
+                if self.is("of") || self.is("in") {
+                    if !init.is_pattern_element() || matches!(init.cover, Cover::WithDefault { .. })
+                    {
+                        return Err(JsError::new(
+                            SourceLocation(start, self.prev_token_end),
+                            "invalid left-hand side in for loop",
+                        ));
+                    }
+                    return self.parse_for_in_or_of_rest();
+                }
+                self.expression_only(&init)?;
+                while self.eat(",")? {
+                    self.parse_assignment(true)?;
+                }
+            }
+        }
+
+        self.expect(";")?;
+        if !self.is(";") {
+            self.parse_expression(false)?;
+        }
+        self.expect(";")?;
+        if !self.is(")") {
+            self.parse_expression(false)?;
+        }
+        self.expect(")")?;
+        self.parse_statement()
+    }
+
+    // Parses the remainder of a for in or for of loop, the parser is expected to be at the in or of keyword
+    fn parse_for_in_or_of_rest(&mut self) -> Result<(), JsError> {
+        if self.eat("of")? {
+            self.parse_assignment(false)?;
+        } else {
+            self.expect("in")?;
+            self.parse_expression(false)?;
+        }
+        self.expect(")")?;
+        self.parse_statement()
+    }
+
+    fn parse_try(&mut self) -> Result<(), JsError> {
+        self.expect("try")?;
+        self.parse_block()?;
+
+        let mut has_handler = false;
+        if self.eat("catch")? {
+            has_handler = true;
+            self.push_scope(false);
+            if self.eat("(")? {
+                self.parse_binding_target()?;
+                self.expect(")")?;
+            }
+            self.parse_block()?;
+            self.pop_scope();
+        }
+        if self.eat("finally")? {
+            has_handler = true;
+            self.parse_block()?;
+        }
+
+        if has_handler {
+            Ok(())
+        } else {
+            Err(JsError::new(
+                self.token.location(),
+                "expected catch or finally after try",
+            ))
+        }
+    }
+
+    fn parse_switch(&mut self) -> Result<(), JsError> {
+        self.expect("switch")?;
+        self.parse_paren_expression()?;
+        self.expect("{")?;
+        self.push_scope(false);
+        while !self.eat("}")? {
+            if self.eat("case")? {
+                self.parse_expression(false)?;
+            } else if !self.eat("default")? {
+                return Err(self.unexpected());
+            }
+            self.expect(":")?;
+            while !self.is("case") && !self.is("default") && !self.is("}") {
+                self.parse_statement()?;
+            }
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    //
+    // Patterns
+    //
+
+    // Parses a variable name or destructuring pattern, used by declarations and function parameters
+    fn parse_binding_target(&mut self) -> Result<(), JsError> {
+        if self.eat("[")? {
+            loop {
+                if self.eat("]")? {
+                    return Ok(());
+                }
+                if self.eat(",")? {
+                    // A hole ([, a])
+                    continue;
+                }
+                if self.eat("...")? {
+                    self.parse_binding_target()?;
+                    self.expect("]")?;
+                    return Ok(());
+                }
+                self.parse_binding_element()?;
+                if !self.is("]") {
+                    self.expect(",")?;
+                }
+            }
+        }
+
+        if self.eat("{")? {
+            loop {
+                if self.eat("}")? {
+                    return Ok(());
+                }
+                if self.eat("...")? {
+                    self.parse_binding_identifier()?;
+                    self.expect("}")?;
+                    return Ok(());
+                }
+
+                let peek = self.peek()?;
+                if self.token.kind == TokenKind::Name && !self.token_is(&peek, ":") {
+                    // Shorthand ({ a } or { a = 1 })
+                    self.parse_binding_identifier()?;
+                    if self.eat("=")? {
+                        self.parse_assignment(false)?;
+                    }
+                } else {
+                    self.parse_property_key(false)?;
+                    self.expect(":")?;
+                    self.parse_binding_element()?;
+                }
+
+                if !self.is("}") {
+                    self.expect(",")?;
+                }
+            }
+        }
+
+        self.parse_binding_identifier()
+    }
+
+    // Parses a binding target with an optional default value (a = 1)
+    fn parse_binding_element(&mut self) -> Result<(), JsError> {
+        self.parse_binding_target()?;
+        if self.eat("=")? {
+            self.parse_assignment(false)?;
+        }
+        Ok(())
+    }
+
+    fn parse_binding_identifier(&mut self) -> Result<(), JsError> {
+        if self.is_identifier() {
+            let name = self.next()?;
+            self.declare(&name);
+            Ok(())
+        } else {
+            Err(JsError::new(
+                self.token.location(),
+                format!("expected a variable name but got {}", self.describe_token()),
+            ))
+        }
+    }
+
+    //
+    // Functions and classes
+    //
+
+    // Changes the parser state for parsing the parameters and body of a function
+    // Returns the outer state that should be restored using exit_function
+    fn enter_function(
+        &mut self,
+        is_async: bool,
+        is_generator: bool,
+        is_arrow: bool,
+    ) -> FunctionContext {
+        let outer = FunctionContext {
+            in_async: self.in_async,
+            in_generator: self.in_generator,
+            in_function: self.in_function,
+            declaring_var: self.declaring_var,
+        };
+        self.in_async = is_async;
+        self.in_generator = is_generator;
+        // Arrow functions use the this and arguments of the outer function
+        self.in_function |= !is_arrow;
+        self.declaring_var = false;
+        outer
+    }
+
+    fn exit_function(&mut self, outer: FunctionContext) {
+        self.in_async = outer.in_async;
+        self.in_generator = outer.in_generator;
+        self.in_function = outer.in_function;
+        self.declaring_var = outer.declaring_var;
+    }
+
+    // Parses a function declaration or expression, the parser is expected to be at the function keyword
+    fn parse_function(&mut self, is_async: bool, is_declaration: bool) -> Result<(), JsError> {
+        self.expect("function")?;
+        let is_generator = self.eat("*")?;
+
+        if self.is_identifier() {
+            let name = self.next()?;
+            if is_declaration {
+                self.declare(&name);
+            } else {
+                // The name of a function expression is only visible within the function itself
+                self.push_scope(false);
+                self.declare(&name);
+                self.parse_function_rest(is_async, is_generator)?;
+                self.pop_scope();
+                return Ok(());
+            }
+        } else if is_declaration {
+            return Err(JsError::new(
+                self.token.location(),
+                "expected a function name",
+            ));
+        }
+
+        self.parse_function_rest(is_async, is_generator)
+    }
+
+    // Parses the parameters and body of a function or method
+    fn parse_function_rest(&mut self, is_async: bool, is_generator: bool) -> Result<(), JsError> {
+        let outer = self.enter_function(is_async, is_generator, false);
+        self.push_scope(true);
+        self.parse_params()?;
+        self.parse_block()?;
+        self.pop_scope();
+        self.exit_function(outer);
+        Ok(())
+    }
+
+    fn parse_params(&mut self) -> Result<(), JsError> {
+        self.expect("(")?;
+        loop {
+            if self.eat(")")? {
+                return Ok(());
+            }
+            if self.eat("...")? {
+                self.parse_binding_target()?;
+                self.expect(")")?;
+                return Ok(());
+            }
+            self.parse_binding_element()?;
+            if !self.is(")") {
+                self.expect(",")?;
+            }
+        }
+    }
+
+    // Parses an arrow function, the parser is expected to be at the parameters
+    fn parse_arrow_function(&mut self, is_async: bool) -> Result<(), JsError> {
+        let outer = self.enter_function(is_async, false, true);
+        self.push_scope(true);
+
+        if self.is("(") {
+            self.parse_params()?;
+        } else {
+            self.parse_binding_identifier()?;
+        }
+        self.expect_arrow()?;
+
+        if self.is("{") {
+            self.parse_block()?;
+        } else {
+            self.parse_assignment(false)?;
+        }
+
+        self.pop_scope();
+        self.exit_function(outer);
+        Ok(())
+    }
+
+    fn parse_class(&mut self, is_declaration: bool) -> Result<(), JsError> {
+        self.expect("class")?;
+        let name = if self.is_identifier() && !self.is("extends") {
+            Some(self.next()?)
+        } else if is_declaration {
+            return Err(JsError::new(self.token.location(), "expected a class name"));
+        } else {
+            None
+        };
+
+        if is_declaration {
+            if let Some(name) = name.as_ref() {
+                self.declare(name);
+            }
+        }
+        // The name of a class expression is only visible within the class itself
+        self.push_scope(false);
+        if !is_declaration {
+            if let Some(name) = name.as_ref() {
+                self.declare(name);
+            }
+        }
+
+        if self.eat("extends")? {
+            let super_class = self.parse_call_member()?;
+            self.expression_only(&super_class)?;
+        }
+
+        self.expect("{")?;
+        while !self.eat("}")? {
+            if self.eat(";")? {
+                continue;
+            }
+            self.parse_class_member()?;
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn parse_class_member(&mut self) -> Result<(), JsError> {
+        if self.is("static") {
+            let peek = self.peek()?;
+            if self.token_is(&peek, "{") {
+                // Static initialization block
+                self.next()?;
+                let outer = self.enter_function(false, false, false);
+                self.parse_block()?;
+                self.exit_function(outer);
+                return Ok(());
+            }
+            if !["(", "=", ";", "}"]
+                .iter()
+                .any(|value| self.token_is(&peek, value))
+            {
+                self.next()?;
+            }
+        }
+
+        let (is_async, is_generator, is_accessor) = self.parse_method_modifiers()?;
+        self.parse_property_key(true)?;
+
+        if self.is("(") {
+            return self.parse_function_rest(is_async, is_generator);
+        }
+        if is_async || is_generator || is_accessor {
+            return Err(self.unexpected());
+        }
+
+        // Class field, within the initializer `this` refers to the class instance
+        if self.eat("=")? {
+            let outer = self.enter_function(false, false, false);
+            self.parse_assignment(false)?;
+            self.exit_function(outer);
+        }
+        self.consume_semicolon()
+    }
+
+    // Parses the async, * (generator), get and set in front of a method name
+    // Returns (is_async, is_generator, is_accessor)
+    fn parse_method_modifiers(&mut self) -> Result<(bool, bool, bool), JsError> {
+        // If one of these follow the modifier keyword it's actually a property or method name ({ get: 1 }, { async() {} })
+        let name_followers = ["(", ":", "=", ",", "}", ";"];
+
+        let mut is_async = false;
+        if self.is("async") {
+            let peek = self.peek()?;
+            if !peek.newline_before && !name_followers.iter().any(|v| self.token_is(&peek, v)) {
+                self.next()?;
+                is_async = true;
+            }
+        }
+
+        let is_generator = self.eat("*")?;
+
+        let mut is_accessor = false;
+        if !is_async && !is_generator && (self.is("get") || self.is("set")) {
+            let peek = self.peek()?;
+            if !name_followers.iter().any(|v| self.token_is(&peek, v)) {
+                self.next()?;
+                is_accessor = true;
+            }
+        }
+
+        Ok((is_async, is_generator, is_accessor))
+    }
+
+    // Parses a property name ({ foo: 1 }, { 'foo': 1 }, { 1: 1 }, { [foo]: 1 })
+    fn parse_property_key(&mut self, allow_private: bool) -> Result<(), JsError> {
+        if self.eat("[")? {
+            self.parse_assignment(false)?;
+            self.expect("]")?;
+            return Ok(());
+        }
+
+        match self.token.kind {
+            TokenKind::Name | TokenKind::String | TokenKind::Number => {}
+            TokenKind::PrivateName if allow_private => {}
+            _ => {
+                return Err(JsError::new(
+                    self.token.location(),
+                    format!("expected a property name but got {}", self.describe_token()),
+                ))
+            }
+        }
+        self.next()?;
+        Ok(())
+    }
+
+    //
+    // Expressions
+    //
+
+    fn parse_expression(&mut self, no_in: bool) -> Result<ExprInfo, JsError> {
+        let first = self.parse_assignment(no_in)?;
+        if !self.is(",") {
+            return Ok(first);
+        }
+        while self.eat(",")? {
+            self.parse_assignment(no_in)?;
+        }
+        Ok(ExprInfo::plain())
+    }
+
+    fn parse_assignment(&mut self, no_in: bool) -> Result<ExprInfo, JsError> {
+        let info = self.parse_assignment_cover(no_in)?;
+        self.expression_only(&info)?;
+        Ok(info)
+    }
+
+    // Parses an assignment expression that might still be converted into a pattern
+    fn parse_assignment_cover(&mut self, no_in: bool) -> Result<ExprInfo, JsError> {
+        if self.in_generator && self.is("yield") {
+            self.next()?;
+            let has_argument = !self.token.newline_before
+                && self.token.kind != TokenKind::Eof
+                && !self.is_one_of(&[")", "]", "}", ",", ";", ":", "in", "of"]);
+            if has_argument {
+                self.eat("*")?;
+                self.parse_assignment(no_in)?;
+            }
+            return Ok(ExprInfo::plain());
+        }
+
+        let start = self.token.start;
+        let left = self.parse_conditional(no_in)?;
+
+        if !self.is_one_of(ASSIGNMENT_OPERATORS) {
+            return Ok(left);
+        }
+
+        let is_plain_assignment = self.is("=");
+        let valid_target = if is_plain_assignment {
+            matches!(
+                left.cover,
+                Cover::Identifier | Cover::Member | Cover::Pattern { .. }
+            )
+        } else {
+            matches!(left.cover, Cover::Identifier | Cover::Member)
+        };
+        if !valid_target {
+            return Err(JsError::new(
+                SourceLocation(start, self.prev_token_end),
+                "invalid assignment target",
+            ));
+        }
+
+        self.next()?;
+        self.parse_assignment(no_in)?;
+
+        Ok(if is_plain_assignment {
+            ExprInfo::cover(Cover::WithDefault {
+                binding: left.is_binding(),
+            })
+        } else {
+            ExprInfo::plain()
+        })
+    }
+
+    fn parse_conditional(&mut self, no_in: bool) -> Result<ExprInfo, JsError> {
+        let test = self.parse_binary(no_in)?;
+        if !self.is("?") {
+            return Ok(test);
+        }
+
+        self.expression_only(&test)?;
+        self.next()?;
+        self.parse_assignment(false)?;
+        self.expect(":")?;
+        self.parse_assignment(no_in)?;
+        Ok(ExprInfo::plain())
+    }
+
+    fn binary_precedence(&self, no_in: bool) -> Option<u8> {
+        match self.token.kind {
+            TokenKind::Punctuator => {}
+            TokenKind::Name if self.is("instanceof") => return Some(7),
+            TokenKind::Name if self.is("in") && !no_in => return Some(7),
+            _ => return None,
+        }
+
+        let precedences: [(&[&str], u8); 11] = [
+            (&["??", "||"], 1),
+            (&["&&"], 2),
+            (&["|"], 3),
+            (&["^"], 4),
+            (&["&"], 5),
+            (&["==", "!=", "===", "!=="], 6),
+            (&["<", ">", "<=", ">="], 7),
+            (&["<<", ">>", ">>>"], 8),
+            (&["+", "-"], 9),
+            (&["*", "/", "%"], 10),
+            (&["**"], 11),
+        ];
+        precedences
+            .iter()
+            .find(|(operators, _)| self.is_one_of(operators))
+            .map(|(_, precedence)| *precedence)
+    }
+
+    fn parse_binary(&mut self, no_in: bool) -> Result<ExprInfo, JsError> {
+        let start = self.token.start;
+        let left = self.parse_unary()?;
+        Ok(self.parse_binary_rest(left, start, 0, no_in)?.0)
+    }
+
+    // Parses the operators following the left side of a binary expression using precedence climbing
+    // Also returns the operator of the parsed binary expression, this is None if there was no operator
+    fn parse_binary_rest(
+        &mut self,
+        mut left: ExprInfo,
+        left_start: usize,
+        min_precedence: u8,
+        no_in: bool,
+    ) -> Result<(ExprInfo, Option<Token>), JsError> {
+        let mut left_operator: Option<Token> = None;
+        loop {
+            let precedence = match self.binary_precedence(no_in) {
+                Some(precedence) if precedence > min_precedence => precedence,
+                _ => return Ok((left, left_operator)),
+            };
+
+            self.expression_only(&left)?;
+            let left_end = self.prev_token_end;
+            let is_exponent = self.is("**");
+            let operator = self.next()?;
+
+            let right_start = self.token.start;
+            let right = self.parse_unary()?;
+            // ** is right associative
+            let right_min_precedence = if is_exponent {
+                precedence - 1
+            } else {
+                precedence
+            };
+            let (right, right_operator) =
+                self.parse_binary_rest(right, right_start, right_min_precedence, no_in)?;
+            self.expression_only(&right)?;
+
+            // a || b ?? c is a syntax error, ?? can only be combined with || and && using parentheses
+            for other in left_operator.iter().chain(right_operator.iter()) {
+                if self.mixes_nullish(&operator, other) {
+                    return Err(JsError::new(
+                        operator.location(),
+                        "?? can not be mixed with || or && without parentheses",
+                    ));
+                }
+            }
+
+            if self.token_is(&operator, "??") {
+                self.rewrites.push(Rewrite::Nullish {
+                    left: SourceLocation(left_start, left_end),
+                    right: SourceLocation(right_start, self.prev_token_end),
+                });
+            }
+
+            left = ExprInfo::plain();
+            left_operator = Some(operator);
+        }
+    }
+
+    fn mixes_nullish(&self, a: &Token, b: &Token) -> bool {
+        let is_logical = |token: &Token| self.token_is(token, "||") || self.token_is(token, "&&");
+        (self.token_is(a, "??") && is_logical(b)) || (is_logical(a) && self.token_is(b, "??"))
+    }
+
+    fn parse_unary(&mut self) -> Result<ExprInfo, JsError> {
+        let is_unary_operator = match self.token.kind {
+            TokenKind::Punctuator => self.is_one_of(&["!", "~", "+", "-"]),
+            TokenKind::Name => {
+                self.is_one_of(&["typeof", "void", "delete"]) || (self.in_async && self.is("await"))
+            }
+            _ => false,
+        };
+        if is_unary_operator {
+            let operator_start = self.token.start;
+            let is_delete = self.is("delete");
+            self.next()?;
+            let argument_start = self.token.start;
+            let argument = self.parse_unary()?;
+            self.expression_only(&argument)?;
+            if is_delete {
+                self.mark_optional_chain_delete(operator_start, argument_start);
+            }
+            return Ok(ExprInfo::plain());
+        }
+
+        if self.is("++") || self.is("--") {
+            self.next()?;
+            let start = self.token.start;
+            let argument = self.parse_unary()?;
+            return self.update_target(argument, start);
+        }
+
+        let start = self.token.start;
+        let expression = self.parse_call_member()?;
+        if (self.is("++") || self.is("--")) && !self.token.newline_before {
+            self.next()?;
+            return self.update_target(expression, start);
+        }
+        Ok(expression)
+    }
+
+    // Makes the optional chain that is the argument of a delete include the delete operator
+    // so it can be lowered to ((_t=foo)==null?true:delete _t.bar)
+    fn mark_optional_chain_delete(&mut self, delete_start: usize, argument_start: usize) {
+        let argument_end = self.prev_token_end;
+        let chain = self.rewrites.iter_mut().find(|rewrite| match rewrite {
+            Rewrite::OptionalChain { object, rest, .. } => {
+                object.0 == argument_start && rest.1 == argument_end
+            }
+            Rewrite::Nullish { .. } => false,
+        });
+        if let Some(Rewrite::OptionalChain {
+            delete_start: chain_delete_start,
+            ..
+        }) = chain
+        {
+            *chain_delete_start = Some(delete_start);
+        }
+    }
+
+    fn update_target(&self, info: ExprInfo, start: usize) -> Result<ExprInfo, JsError> {
+        if matches!(info.cover, Cover::Identifier | Cover::Member) {
+            Ok(ExprInfo::plain())
+        } else {
+            Err(JsError::new(
+                SourceLocation(start, self.prev_token_end),
+                "invalid increment or decrement target",
+            ))
+        }
+    }
+
+    // Parses member expressions, calls and tagged templates (foo.bar[baz](1)`a`)
+    fn parse_call_member(&mut self) -> Result<ExprInfo, JsError> {
+        let start = self.token.start;
+        let mut info = if self.is("new") {
+            self.parse_new()?
+        } else {
+            self.parse_primary()?
+        };
+        let mut optional_chain = false;
+        // The index of the last optional chain rewrite of this chain, its rest is updated as the chain grows
+        let mut last_optional: Option<usize> = None;
+        // The start of the last property access, used to find the object of a method call
+        let mut last_member_start: Option<usize> = None;
+
+        loop {
+            let is_suffix = self.is_one_of(&[".", "?.", "[", "("])
+                || matches!(self.token.kind, TokenKind::Template(_));
+            if !is_suffix {
+                break;
+            }
+            self.expression_only(&info)?;
+            let suffix_start = self.token.start;
+
+            if self.eat(".")? {
+                self.parse_member_name()?;
+                info = ExprInfo::cover(Cover::Member);
+                last_member_start = Some(suffix_start);
+            } else if self.is("?.") {
+                optional_chain = true;
+                self.update_optional_rest(last_optional);
+                let object = SourceLocation(start, self.prev_token_end);
+                self.next()?;
+                let rest_start = self.token.start;
+
+                let kind = if self.is("(") {
+                    let this_object = last_member_start
+                        .map(|member_start| self.method_object(start, member_start, last_optional));
+                    let next = self.peek()?;
+                    let has_arguments = !self.token_is(&next, ")");
+                    self.parse_arguments()?;
+                    last_member_start = None;
+                    OptionalKind::Call {
+                        this_object,
+                        has_arguments,
+                    }
+                } else if self.eat("[")? {
+                    self.parse_expression(false)?;
+                    self.expect("]")?;
+                    last_member_start = Some(suffix_start);
+                    OptionalKind::Computed
+                } else {
+                    self.parse_member_name()?;
+                    last_member_start = Some(suffix_start);
+                    OptionalKind::Member
+                };
+
+                self.rewrites.push(Rewrite::OptionalChain {
+                    object,
+                    rest: SourceLocation(rest_start, self.prev_token_end),
+                    kind,
+                    delete_start: None,
+                });
+                last_optional = Some(self.rewrites.len() - 1);
+                info = ExprInfo::plain();
+            } else if self.eat("[")? {
+                self.parse_expression(false)?;
+                self.expect("]")?;
+                info = ExprInfo::cover(Cover::Member);
+                last_member_start = Some(suffix_start);
+            } else if self.is("(") {
+                self.parse_arguments()?;
+                info = ExprInfo::plain();
+                last_member_start = None;
+            } else {
+                if optional_chain {
+                    return Err(JsError::new(
+                        self.token.location(),
+                        "tagged template literals can not be used in an optional chain",
+                    ));
+                }
+                self.parse_template()?;
+                info = ExprInfo::plain();
+                last_member_start = None;
+            }
+        }
+        self.update_optional_rest(last_optional);
+
+        if optional_chain {
+            // An optional chain can never be assigned to
+            info = ExprInfo::plain();
+        }
+        Ok(info)
+    }
+
+    // Makes the rest of an optional chain rewrite go up to the current position
+    fn update_optional_rest(&mut self, rewrite: Option<usize>) {
+        let end = self.prev_token_end;
+        if let Some(Rewrite::OptionalChain { rest, .. }) =
+            rewrite.and_then(|idx| self.rewrites.get_mut(idx))
+        {
+            rest.1 = end;
+        }
+    }
+
+    // Returns the location of the object of a method called using ?.()
+    // The object starts at the chain start or, if the method is accessed after an optional
+    // chain, at the rest of that optional chain (foo?.bar.baz?.() has the object bar)
+    fn method_object(
+        &self,
+        chain_start: usize,
+        member_start: usize,
+        last_optional: Option<usize>,
+    ) -> SourceLocation {
+        match last_optional.and_then(|idx| self.rewrites.get(idx)) {
+            Some(Rewrite::OptionalChain { rest, .. }) if rest.0 <= member_start => {
+                SourceLocation(rest.0, member_start)
+            }
+            _ => SourceLocation(chain_start, member_start),
+        }
+    }
+
+    fn parse_member_name(&mut self) -> Result<(), JsError> {
+        match self.token.kind {
+            TokenKind::Name | TokenKind::PrivateName => {
+                self.next()?;
+                Ok(())
+            }
+            _ => Err(JsError::new(
+                self.token.location(),
+                format!("expected a property name but got {}", self.describe_token()),
+            )),
+        }
+    }
+
+    fn parse_new(&mut self) -> Result<ExprInfo, JsError> {
+        self.expect("new")?;
+        if self.eat(".")? {
+            // new.target
+            self.expect("target")?;
+            return Ok(ExprInfo::plain());
+        }
+
+        if self.is("new") {
+            self.parse_new()?;
+        } else {
+            let callee = self.parse_primary()?;
+            self.expression_only(&callee)?;
+        }
+
+        // The callee might be a member expression, the first call arguments belong to the new expression
+        loop {
+            if self.eat(".")? {
+                self.parse_member_name()?;
+            } else if self.eat("[")? {
+                self.parse_expression(false)?;
+                self.expect("]")?;
+            } else if let TokenKind::Template(_) = self.token.kind {
+                self.parse_template()?;
+            } else if self.is("?.") {
+                return Err(JsError::new(
+                    self.token.location(),
+                    "optional chains can not be used in a new expression",
+                ));
+            } else {
+                break;
+            }
+        }
+
+        if self.is("(") {
+            self.parse_arguments()?;
+        }
+        Ok(ExprInfo::plain())
+    }
+
+    fn parse_arguments(&mut self) -> Result<(), JsError> {
+        self.expect("(")?;
+        while !self.eat(")")? {
+            self.eat("...")?;
+            self.parse_assignment(false)?;
+            if !self.is(")") {
+                self.expect(",")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_primary(&mut self) -> Result<ExprInfo, JsError> {
+        match self.token.kind {
+            TokenKind::Number | TokenKind::String => {
+                self.next()?;
+                Ok(ExprInfo::plain())
+            }
+            TokenKind::Template(_) => {
+                self.parse_template()?;
+                Ok(ExprInfo::plain())
+            }
+            TokenKind::PrivateName => {
+                // Only valid as the left side of an in check (#foo in bar)
+                self.next()?;
+                if !self.is("in") {
+                    return Err(self.unexpected());
+                }
+                Ok(ExprInfo::plain())
+            }
+            TokenKind::Name => self.parse_primary_name(),
+            TokenKind::Punctuator => {
+                if self.is("(") {
+                    self.parse_paren_or_arrow(None)
+                } else if self.is("[") {
+                    self.parse_array_literal()
+                } else if self.is("{") {
+                    self.parse_object_literal()
+                } else if self.is("/") || self.is("/=") {
+                    self.token = self.tokenizer.rescan_regex(&self.token)?;
+                    self.next()?;
+                    Ok(ExprInfo::plain())
+                } else {
+                    Err(self.unexpected())
+                }
+            }
+            TokenKind::Regex | TokenKind::Eof => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_primary_name(&mut self) -> Result<ExprInfo, JsError> {
+        if self.is("this") {
+            let this = self.next()?;
+            self.add_reference(&this, false);
+            return Ok(ExprInfo::plain());
+        }
+        if self.is_one_of(&["null", "true", "false"]) {
+            self.next()?;
+            return Ok(ExprInfo::plain());
+        }
+        if self.is("function") {
+            self.parse_function(false, false)?;
+            return Ok(ExprInfo::cover(Cover::Function));
+        }
+        if self.is("class") {
+            self.parse_class(false)?;
+            return Ok(ExprInfo::plain());
+        }
+        if self.is("super") {
+            self.next()?;
+            if !self.is_one_of(&["(", ".", "["]) {
+                return Err(self.unexpected());
+            }
+            return Ok(ExprInfo::plain());
+        }
+        if self.is("import") {
+            // import(..) or import.meta
+            self.next()?;
+            if self.eat(".")? {
+                self.expect("meta")?;
+            } else if !self.is("(") {
+                return Err(self.unexpected());
+            }
+            return Ok(ExprInfo::plain());
+        }
+
+        if self.is("async") {
+            let peek = self.peek()?;
+            if !peek.newline_before {
+                if self.token_is(&peek, "function") {
+                    self.next()?;
+                    self.parse_function(true, false)?;
+                    return Ok(ExprInfo::cover(Cover::Function));
+                }
+                if peek.kind == TokenKind::Name {
+                    // async foo => ..
+                    self.next()?;
+                    self.parse_arrow_function(true)?;
+                    return Ok(ExprInfo::cover(Cover::Function));
+                }
+                if self.token_is(&peek, "(") {
+                    // async (foo) => .. or a call to a function named async
+                    let async_token = self.next()?;
+                    return self.parse_paren_or_arrow(Some(async_token));
+                }
+            }
+        }
+
+        if !self.is_identifier() {
+            return Err(self.unexpected());
+        }
+
+        let peek = self.peek()?;
+        if self.token_is(&peek, "=>") && !peek.newline_before {
+            // foo => ..
+            self.parse_arrow_function(false)?;
+            return Ok(ExprInfo::cover(Cover::Function));
+        }
+
+        let name = self.next()?;
+        self.add_reference(&name, false);
+        Ok(ExprInfo::cover(Cover::Identifier))
+    }
+
+    fn expect_arrow(&mut self) -> Result<(), JsError> {
+        if self.token.newline_before {
+            return Err(JsError::new(
+                self.token.location(),
+                "unexpected newline before =>",
+            ));
+        }
+        self.expect("=>")?;
+        Ok(())
+    }
+
+    // Parses a parenthesized expression or the parameters of an arrow function
+    // If there is an async_token these might also be the arguments of a call to a function named async
+    fn parse_paren_or_arrow(&mut self, async_token: Option<Token>) -> Result<ExprInfo, JsError> {
+        let is_async_call = async_token.is_some();
+        let references_len = self.references.len();
+        let start = self.expect("(")?.start;
+        let mut items: Vec<ExprInfo> = Vec::new();
+        let mut params = PatternState::new();
+        let mut only_valid_as_params = false;
+
+        loop {
+            if self.eat(")")? {
+                if items.is_empty() {
+                    only_valid_as_params = true;
+                }
+                break;
+            }
+            if self.eat("...")? {
+                let rest = self.parse_assignment_cover(false)?;
+                params.add_rest(&rest, !self.is(","), true);
+                only_valid_as_params = true;
+                if !is_async_call || !self.is(",") {
+                    self.expect(")")?;
+                    break;
+                }
+                self.next()?;
+                continue;
+            }
+
+            let item = self.parse_assignment_cover(false)?;
+            params.add(&item);
+            items.push(item);
+
+            if self.eat(")")? {
+                break;
+            }
+            self.expect(",")?;
+            if self.eat(")")? {
+                // Trailing comma
+                only_valid_as_params = true;
+                break;
+            }
+        }
+
+        if self.is("=>") {
+            self.expect_arrow()?;
+            if !params.binding {
+                return Err(JsError::new(
+                    SourceLocation(start, self.prev_token_end),
+                    "invalid arrow function parameters",
+                ));
+            }
+
+            // The parameters where parsed as expressions, parse them again as parameters of the arrow function
+            self.references.truncate(references_len);
+            self.tokenizer.set_pos(start);
+            self.token = self.tokenizer.next_token()?;
+            self.parse_arrow_function(is_async_call)?;
+            return Ok(ExprInfo::cover(Cover::Function));
+        }
+
+        if let Some(async_token) = async_token {
+            self.add_reference(&async_token, false);
+            // This is a function call, the arguments are normal expressions
+            for item in items.iter() {
+                self.expression_only(item)?;
+            }
+            return Ok(ExprInfo::plain());
+        }
+
+        if only_valid_as_params {
+            return Err(JsError::new(
+                self.token.location(),
+                format!("expected '=>' but got {}", self.describe_token()),
+            ));
+        }
+        for item in items.iter() {
+            self.expression_only(item)?;
+        }
+
+        // Only a parenthesized variable or property can be assigned to ((foo) = 1)
+        match items.as_slice() {
+            [item] if matches!(item.cover, Cover::Identifier | Cover::Member) => {
+                Ok(ExprInfo::cover(item.cover))
+            }
+            _ => Ok(ExprInfo::plain()),
+        }
+    }
+
+    fn parse_array_literal(&mut self) -> Result<ExprInfo, JsError> {
+        self.expect("[")?;
+        let mut state = PatternState::new();
+
+        loop {
+            if self.eat("]")? {
+                break;
+            }
+            if self.eat(",")? {
+                // A hole ([, a])
+                continue;
+            }
+
+            if self.eat("...")? {
+                let spread = self.parse_assignment_cover(false)?;
+                let is_last = self.is("]");
+                if !is_last {
+                    self.expression_only(&spread)?;
+                }
+                state.add_rest(&spread, is_last, true);
+            } else {
+                let element = self.parse_assignment_cover(false)?;
+                state.add(&element);
+            }
+
+            if !self.is("]") {
+                self.expect(",")?;
+            }
+        }
+
+        state.into_info()
+    }
+
+    fn parse_object_literal(&mut self) -> Result<ExprInfo, JsError> {
+        self.expect("{")?;
+        let mut state = PatternState::new();
+
+        loop {
+            if self.eat("}")? {
+                break;
+            }
+
+            if self.eat("...")? {
+                let spread = self.parse_assignment_cover(false)?;
+                self.expression_only(&spread)?;
+                state.add_rest(&spread, self.is("}"), false);
+            } else {
+                self.parse_object_member(&mut state)?;
+            }
+
+            if !self.is("}") {
+                self.expect(",")?;
+            }
+        }
+
+        state.into_info()
+    }
+
+    fn parse_object_member(&mut self, state: &mut PatternState) -> Result<(), JsError> {
+        let (is_async, is_generator, is_accessor) = self.parse_method_modifiers()?;
+
+        let key = self.token;
+        let is_shorthand_candidate = key.kind == TokenKind::Name;
+        self.parse_property_key(false)?;
+
+        if self.is("(") {
+            // A method ({ foo() {} })
+            self.parse_function_rest(is_async, is_generator)?;
+            state.add_invalid();
+            return Ok(());
+        }
+        if is_async || is_generator || is_accessor {
+            return Err(JsError::new(
+                self.token.location(),
+                format!("expected '(' but got {}", self.describe_token()),
+            ));
+        }
+
+        if self.eat(":")? {
+            let value = self.parse_assignment_cover(false)?;
+            state.add(&value);
+            return Ok(());
+        }
+
+        if !is_shorthand_candidate {
+            return Err(JsError::new(
+                self.token.location(),
+                format!("expected ':' but got {}", self.describe_token()),
+            ));
+        }
+        if self.is_reserved_word(&key) {
+            return Err(JsError::new(
+                key.location(),
+                "reserved words can not be used as shorthand properties",
+            ));
+        }
+        self.add_reference(&key, true);
+
+        if self.is("=") {
+            // Shorthand with initializer ({ a = 1 }), only valid if this object becomes a pattern
+            let cover_init = self.next()?.start;
+            self.parse_assignment(false)?;
+            state.add(&ExprInfo {
+                cover: Cover::WithDefault { binding: true },
+                cover_init: Some(cover_init),
+            });
+        } else {
+            state.add(&ExprInfo::cover(Cover::Identifier));
+        }
+        Ok(())
+    }
+
+    // Parses a template literal including its substitutions
+    fn parse_template(&mut self) -> Result<(), JsError> {
+        loop {
+            let has_substitution = matches!(self.token.kind, TokenKind::Template(true));
+            self.next()?;
+            if !has_substitution {
+                return Ok(());
+            }
+
+            self.parse_expression(false)?;
+            if !self.is("}") {
+                return Err(JsError::new(
+                    self.token.location(),
+                    format!("expected '}}' but got {}", self.describe_token()),
+                ));
+            }
+            self.token = self.tokenizer.rescan_template_continuation(&self.token)?;
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::super::{Parser, SourceLocation};
+use super::parser::{OptionalKind, ParsedJs, Reference, Rewrite};
+use super::write_reference;
+
+/*
+    Writes template javascript with the references to the component prefixed with `_vm.`
+
+    If lower is enabled optional chains and nullish coalescing are rewritten into ES5 compatible javascript:
+    foo?.bar        -> ((_t=_vm.foo)==null?void 0:_t.bar)
+    foo ?? bar      -> ((_t=_vm.foo)!=null?_t:_vm.bar)
+    delete foo?.bar -> ((_t=_vm.foo)==null?true:delete _t.bar)
+
+    The temporary variable is always read directly after it's assigned so nested rewrites can share it.
+    Methods called using ?.() also keep a reference to their object (_o) so it can be used as `this`:
+    foo.bar?.() -> ((_t=(_o=_vm.foo).bar)==null?void 0:_t.call(_o))
+*/
+
+// The temporary variables used by the rewritten javascript, these need to be declared in the render function
+pub const TEMP_VARIABLES: &str = "_t,_o";
+
+// Assigns the object of a method to _o, the object goes from open_at up to close_at
+struct MethodObject {
+    open_at: usize,
+    close_at: usize,
+    // The depth at which the opening was written, the closing must be written at the same depth
+    opened_depth: Option<usize>,
+    closed: bool,
+}
+
+pub struct JsPrinter<'a> {
+    p: &'a Parser,
+    references: &'a [Reference],
+    rewrites: &'a [Rewrite],
+    method_objects: Vec<MethodObject>,
+    resp: String,
+}
+
+impl<'a> JsPrinter<'a> {
+    pub fn new(p: &'a Parser, parsed: &'a ParsedJs, lower: bool) -> Self {
+        let rewrites: &[Rewrite] = if lower { &parsed.rewrites } else { &[] };
+
+        let method_objects = rewrites
+            .iter()
+            .filter_map(|rewrite| match rewrite {
+                Rewrite::OptionalChain {
+                    kind:
+                        OptionalKind::Call {
+                            this_object: Some(this_object),
+                            ..
+                        },
+                    ..
+                } => Some(MethodObject {
+                    open_at: this_object.0,
+                    close_at: this_object.1,
+                    opened_depth: None,
+                    closed: false,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            p,
+            references: &parsed.references,
+            rewrites,
+            method_objects,
+            resp: String::new(),
+        }
+    }
+
+    pub fn print(mut self, js: &SourceLocation) -> String {
+        self.print_range(js.0, js.1, 0);
+        self.resp
+    }
+
+    fn print_range(&mut self, start: usize, end: usize, depth: usize) {
+        let mut pos = start;
+        loop {
+            self.write_method_object_closings(pos, depth);
+            if pos >= end {
+                return;
+            }
+            self.write_method_object_openings(pos, end, depth, None);
+
+            if let Some(rewrite) = self.outermost_rewrite_at(pos, end) {
+                self.print_rewrite(&rewrite, depth + 1);
+                pos = rewrite.location().1;
+                continue;
+            }
+            if let Some(reference) = self
+                .references
+                .iter()
+                .find(|reference| reference.location.0 == pos)
+            {
+                write_reference(self.p, reference, &mut self.resp);
+                pos = reference.location.1;
+                continue;
+            }
+
+            // Copy the source up to the next position where something has to be written
+            let mut next = end;
+            let reference_starts = self.references.iter().map(|reference| reference.location.0);
+            let rewrite_starts = self.rewrites.iter().map(|rewrite| rewrite.location().0);
+            let method_object_bounds = self
+                .method_objects
+                .iter()
+                .flat_map(|method_object| [method_object.open_at, method_object.close_at]);
+            for at in reference_starts
+                .chain(rewrite_starts)
+                .chain(method_object_bounds)
+            {
+                if at > pos && at < next {
+                    next = at;
+                }
+            }
+
+            self.resp
+                .push_str(&SourceLocation(pos, next).string(self.p));
+            pos = next;
+        }
+    }
+
+    // Returns the rewrite that starts at pos and is not part of another rewrite that starts at pos
+    fn outermost_rewrite_at(&self, pos: usize, end: usize) -> Option<Rewrite> {
+        self.rewrites
+            .iter()
+            .filter(|rewrite| {
+                let location = rewrite.location();
+                location.0 == pos && location.1 <= end
+            })
+            .max_by_key(|rewrite| rewrite.location().1)
+            .cloned()
+    }
+
+    fn print_rewrite(&mut self, rewrite: &Rewrite, depth: usize) {
+        match rewrite {
+            Rewrite::Nullish { left, right } => {
+                self.resp.push_str("((_t=");
+                self.print_range(left.0, left.1, depth);
+                self.resp.push_str(")!=null?_t:");
+                self.print_range(right.0, right.1, depth);
+                self.resp.push(')');
+            }
+            Rewrite::OptionalChain {
+                object,
+                rest,
+                kind,
+                delete_start,
+            } => {
+                self.resp.push_str("((_t=");
+                self.print_range(object.0, object.1, depth);
+                if delete_start.is_some() {
+                    // Deleting from a nullish object short circuits to true
+                    self.resp.push_str(")==null?true:delete ");
+                } else {
+                    self.resp.push_str(")==null?void 0:");
+                }
+
+                // A method object that starts at the rest also contains the temporary variable
+                self.write_method_object_openings(rest.0, rest.1, depth, Some(rest.0));
+                self.resp.push_str("_t");
+
+                match kind {
+                    OptionalKind::Member => {
+                        self.resp.push('.');
+                        self.print_range(rest.0, rest.1, depth);
+                    }
+                    OptionalKind::Computed
+                    | OptionalKind::Call {
+                        this_object: None, ..
+                    } => {
+                        self.print_range(rest.0, rest.1, depth);
+                    }
+                    OptionalKind::Call {
+                        this_object: Some(_),
+                        has_arguments,
+                    } => {
+                        // Skip the ( as it's replaced by .call(_o,
+                        self.resp.push_str(".call(_o");
+                        if *has_arguments {
+                            self.resp.push(',');
+                        }
+                        self.print_range(rest.0 + 1, rest.1, depth);
+                    }
+                }
+                self.resp.push(')');
+            }
+        }
+    }
+
+    // Writes the (_o= for method objects that start at pos
+    // If a rewrite that starts at pos contains the whole method object the opening is written within that rewrite
+    fn write_method_object_openings(
+        &mut self,
+        pos: usize,
+        end: usize,
+        depth: usize,
+        rewrite_rest_start: Option<usize>,
+    ) {
+        let outer_rewrite_end = match rewrite_rest_start {
+            Some(_) => None,
+            None => self
+                .outermost_rewrite_at(pos, end)
+                .map(|rewrite| rewrite.location().1),
+        };
+
+        for method_object in self.method_objects.iter_mut() {
+            if method_object.open_at != pos || method_object.opened_depth.is_some() {
+                continue;
+            }
+            if let Some(outer_rewrite_end) = outer_rewrite_end {
+                if outer_rewrite_end > method_object.close_at {
+                    continue;
+                }
+            }
+            method_object.opened_depth = Some(depth);
+            self.resp.push_str("(_o=");
+        }
+    }
+
+    fn write_method_object_closings(&mut self, pos: usize, depth: usize) {
+        for method_object in self.method_objects.iter_mut() {
+            if method_object.close_at == pos
+                && method_object.opened_depth == Some(depth)
+                && !method_object.closed
+            {
+                method_object.closed = true;
+                self.resp.push(')');
+            }
+        }
+    }
+}
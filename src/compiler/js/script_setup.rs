@@ -0,0 +1,1447 @@
+use super::super::{Parser, ParserError, ScriptSetup, SourceLocation};
+use super::tokenizer::{is_name_part, is_name_start, JsError, Token, TokenKind, Tokenizer};
+use super::ts_types::{self, TsMember, TypeDeclaration};
+
+/*
+    Compiles <script setup> into a component with a setup function
+
+    <script setup>
+    import { ref } from 'vue'
+    const props = defineProps({ title: String })
+    const count = ref(0)
+    </script>
+
+    Becomes:
+
+    import { ref } from 'vue'
+    export default {
+    props: { title: String },
+    setup(__props, { expose: __expose, emit: __emit }) {
+    const props = __props
+    const count = ref(0)
+    return { ref, props, count }
+    }
+    };
+
+    The script is not fully parsed, only the top level statements are inspected to find the imports,
+    the declared bindings and the compiler macros (defineProps, defineEmits and defineExpose).
+    This keeps <script setup lang="ts"> working as the types are left as is for vite to strip.
+*/
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptSetupInfo {
+    // The import statements, these are hoisted out of the setup function
+    pub imports: Vec<Import>,
+    // The names declared on the top level of the script
+    pub bindings: Vec<String>,
+    pub define_props: Option<MacroCall>,
+    pub define_emits: Option<MacroCall>,
+    pub define_expose: Option<MacroCall>,
+    // withDefaults(defineProps<..>(), { .. })
+    pub with_defaults: Option<WithDefaults>,
+    // The typescript interfaces and type aliases, used to generate the props and emits of defineProps<..>() and defineEmits<..>()
+    pub type_declarations: Vec<TypeDeclaration>,
+}
+
+impl ScriptSetupInfo {
+    // Returns true if the name is imported or declared on the top level of the script
+    pub fn has_binding(&self, name: &str) -> bool {
+        self.bindings.iter().any(|binding| binding == name)
+            || self
+                .imports
+                .iter()
+                .any(|import| import.bindings.iter().any(|binding| binding == name))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    // The full import statement including the semicolon
+    pub location: SourceLocation,
+    // The imported names, type only imports (import type { Foo } from 'foo') are excluded
+    pub bindings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroCall {
+    // From the name of the macro up to and including the closing parenthesis
+    pub location: SourceLocation,
+    // The name of the macro
+    pub name: SourceLocation,
+    // The type arguments within the < >, only used by typescript
+    pub type_arguments: Option<SourceLocation>,
+    // The arguments within the ( )
+    pub arguments: SourceLocation,
+}
+
+#[derive(Debug, Clone)]
+pub struct WithDefaults {
+    // The full withDefaults(..) call, this includes the defineProps call
+    pub location: SourceLocation,
+    pub defaults: Vec<PropDefault>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropDefault {
+    pub key: String,
+    // The value of the property, for methods (foo() { return [] }) this starts at the parameters
+    pub value: SourceLocation,
+    pub is_method: bool,
+}
+
+pub fn analyze(chars: &[char], location: &SourceLocation) -> Result<ScriptSetupInfo, JsError> {
+    let (tokens, depths) = tokenize(chars, location)?;
+    let mut analyzer = Analyzer {
+        chars,
+        tokens,
+        depths,
+        info: ScriptSetupInfo::default(),
+        normal_script: false,
+        default_export_specifier: false,
+    };
+    analyzer.find_macros()?;
+    analyzer.find_with_defaults()?;
+    analyzer.find_top_level_await()?;
+    analyzer.find_bindings()?;
+    analyzer.find_type_declarations();
+    Ok(analyzer.info)
+}
+
+// Finds the typescript interfaces and type aliases of a normal <script>
+fn analyze_type_declarations(
+    chars: &[char],
+    location: &SourceLocation,
+) -> Result<Vec<TypeDeclaration>, JsError> {
+    let (tokens, depths) = tokenize(chars, location)?;
+    let mut analyzer = Analyzer {
+        chars,
+        tokens,
+        depths,
+        info: ScriptSetupInfo::default(),
+        normal_script: true,
+        default_export_specifier: false,
+    };
+    analyzer.find_type_declarations();
+    Ok(analyzer.info.type_declarations)
+}
+
+// Returns the names a normal <script> declares or imports on the top level
+// Returns None if the default export can't be rewritten in place (export { foo as default })
+fn analyze_normal_script_bindings(
+    chars: &[char],
+    location: &SourceLocation,
+) -> Result<Option<Vec<String>>, JsError> {
+    let (tokens, depths) = tokenize(chars, location)?;
+    let mut analyzer = Analyzer {
+        chars,
+        tokens,
+        depths,
+        info: ScriptSetupInfo::default(),
+        normal_script: true,
+        default_export_specifier: false,
+    };
+    analyzer.find_bindings()?;
+    if analyzer.default_export_specifier {
+        return Ok(None);
+    }
+
+    let mut bindings = analyzer.info.bindings;
+    for import in analyzer.info.imports {
+        bindings.extend(import.bindings);
+    }
+    Ok(Some(bindings))
+}
+
+// Writes the module that exports the component, the normal <script> is merged with the component created by <script setup>
+pub fn compile(p: &Parser, setup: &ScriptSetup) -> Result<String, ParserError> {
+    compile_component(p, setup, "export default ")
+}
+
+// Compiles the script(s) of a component so they can be placed within the main module,
+// the component is assigned to c instead of being exported
+// Returns None if the script must be imported from a separate module:
+// - the lang needs to be transformed by vite (typescript, jsx, ..)
+// - the script is imported using <script src="..">
+// - a name declared by the script is also declared by the main module (is_reserved)
+// - the default export is not a export default statement
+pub fn compile_inline(
+    p: &Parser,
+    is_reserved: impl Fn(&str) -> bool,
+) -> Result<Option<String>, ParserError> {
+    let (lang, setup) = match (p.script_setup.as_ref(), p.script.as_ref()) {
+        (Some(setup), _) => (setup.lang.as_deref(), Some(setup)),
+        (None, Some(script)) => (script.lang.as_deref(), None),
+        (None, None) => return Ok(None),
+    };
+    if !matches!(lang, None | Some("js")) {
+        return Ok(None);
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    if let Some(script) = p.script.as_ref() {
+        if script.src.is_some() {
+            return Ok(None);
+        }
+        match analyze_normal_script_bindings(&p.source_chars, &script.content)
+            .map_err(|err| ParserError::new_at(p, &err.location, err.message))?
+        {
+            Some(mut bindings) => names.append(&mut bindings),
+            None => return Ok(None),
+        }
+    }
+    if let Some(setup) = setup {
+        // Only the imports of <script setup> are placed on the top level, the rest is part of the setup function
+        for import in setup.info.imports.iter() {
+            names.extend(import.bindings.iter().cloned());
+        }
+    }
+    if names.iter().any(|name| is_reserved(name)) {
+        return Ok(None);
+    }
+
+    let resp = match (setup, p.script.as_ref()) {
+        (Some(setup), _) => compile_component(p, setup, "const c = ")?,
+        (None, Some(script)) => {
+            let mut resp = String::new();
+            write_script_without_default_export(p, &script.content, &mut resp)?;
+            resp.push_str("\nconst c = __default__ || {};");
+            resp
+        }
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(resp))
+}
+
+// Writes the component created by <script setup>, the component is written after the value of export
+fn compile_component(p: &Parser, setup: &ScriptSetup, export: &str) -> Result<String, ParserError> {
+    let info = &setup.info;
+    let mut resp = String::new();
+
+    let has_normal_script = if let Some(script) = p.script.as_ref() {
+        if let Some(src) = script.src.as_ref() {
+            // <script src=".."> only provides the default export
+            resp.push_str("import __default__ from ");
+            resp.push_str(&quote(src));
+            resp.push(';');
+        } else {
+            write_script_without_default_export(p, &script.content, &mut resp)?;
+        }
+        resp.push('\n');
+        true
+    } else {
+        false
+    };
+
+    for import in info.imports.iter() {
+        import.location.write_to_string(p, &mut resp);
+        resp.push('\n');
+    }
+
+    resp.push_str(export);
+    if has_normal_script {
+        resp.push_str("Object.assign(__default__, ");
+    }
+    resp.push_str("{\n");
+
+    let mut type_declarations = info.type_declarations.clone();
+    if let Some(script) = p.script.as_ref() {
+        if info.define_props.is_some() || info.define_emits.is_some() {
+            let mut script_type_declarations =
+                analyze_type_declarations(&p.source_chars, &script.content)
+                    .map_err(|err| ParserError::new_at(p, &err.location, err.message))?;
+            type_declarations.append(&mut script_type_declarations);
+        }
+    }
+
+    if let Some(define_props) = info.define_props.as_ref() {
+        let defaults = match info.with_defaults.as_ref() {
+            Some(with_defaults) => runtime_defaults(p, with_defaults),
+            None => Vec::new(),
+        };
+        if let Some(props) =
+            macro_runtime_value(p, define_props, &type_declarations, Some(&defaults))?
+        {
+            resp.push_str("props: ");
+            resp.push_str(&props);
+            resp.push_str(",\n");
+        }
+    }
+    if let Some(define_emits) = info.define_emits.as_ref() {
+        if let Some(emits) = macro_runtime_value(p, define_emits, &type_declarations, None)? {
+            resp.push_str("emits: ");
+            resp.push_str(&emits);
+            resp.push_str(",\n");
+        }
+    }
+    resp.push_str("setup(__props, { expose: __expose, emit: __emit }) {");
+
+    // The setup function body is the script without the imports and with the macros replaced
+    let mut replacements: Vec<(&SourceLocation, &str)> = Vec::new();
+    for import in info.imports.iter() {
+        replacements.push((&import.location, ""));
+    }
+    if let Some(with_defaults) = info.with_defaults.as_ref() {
+        replacements.push((&with_defaults.location, "__props"));
+    } else if let Some(define_props) = info.define_props.as_ref() {
+        replacements.push((&define_props.location, "__props"));
+    }
+    if let Some(define_emits) = info.define_emits.as_ref() {
+        replacements.push((&define_emits.location, "__emit"));
+    }
+    if let Some(define_expose) = info.define_expose.as_ref() {
+        replacements.push((&define_expose.name, "__expose"));
+    }
+    replacements.sort_by_key(|(location, _)| location.0);
+
+    let mut pos = setup.content.0;
+    for (location, replacement) in replacements {
+        SourceLocation(pos, location.0).write_to_string(p, &mut resp);
+        resp.push_str(replacement);
+        pos = location.1;
+    }
+    SourceLocation(pos, setup.content.1).write_to_string(p, &mut resp);
+
+    resp.push_str("\nreturn { ");
+    resp.push_str(&returned_bindings(p, setup).join(", "));
+    resp.push_str(" }\n}\n}");
+    if has_normal_script {
+        resp.push(')');
+    }
+    resp.push_str(";\n");
+
+    Ok(resp)
+}
+
+// Returns the runtime declaration of the props or emits,
+// this is either the argument of the macro or generated from the type argument
+// props_defaults is only set for the props, these are the defaults of withDefaults
+fn macro_runtime_value(
+    p: &Parser,
+    call: &MacroCall,
+    type_declarations: &[TypeDeclaration],
+    props_defaults: Option<&[(String, String)]>,
+) -> Result<Option<String>, ParserError> {
+    let is_props = props_defaults.is_some();
+    let macro_name = if is_props {
+        "defineProps"
+    } else {
+        "defineEmits"
+    };
+
+    let type_arguments = match call.type_arguments.as_ref() {
+        Some(type_arguments) => type_arguments,
+        None if call.arguments.is_empty() => return Ok(None),
+        None => return Ok(Some(call.arguments.string(p))),
+    };
+    if !call.arguments.is_empty() {
+        return Err(ParserError::new_at(
+            p,
+            &call.location,
+            format!(
+                "{}() cannot accept both type and non-type arguments at the same time",
+                macro_name
+            ),
+        ));
+    }
+
+    let type_ = ts_types::parse_type(&p.source_chars, type_arguments)
+        .map_err(|err| ParserError::new_at(p, &err.location, err.message))?;
+    let members = match ts_types::resolve_members(&type_, type_declarations) {
+        Some(members) => members,
+        None => {
+            return Err(ParserError::new_at(
+                p,
+                type_arguments,
+                format!(
+                    "type argument passed to {}() must be a literal type, or a reference to an interface or literal type",
+                    macro_name
+                ),
+            ))
+        }
+    };
+
+    Ok(Some(match props_defaults {
+        Some(defaults) => runtime_props(&members, type_declarations, defaults),
+        None => runtime_emits(&members, type_declarations),
+    }))
+}
+
+// Returns the default entry of the runtime props for every default of withDefaults
+// { size: 'small', items() { return [] } } -> [(size, default: 'small'), (items, default() { return [] })]
+fn runtime_defaults(p: &Parser, with_defaults: &WithDefaults) -> Vec<(String, String)> {
+    with_defaults
+        .defaults
+        .iter()
+        .map(|default| {
+            let entry = if default.is_method {
+                format!("default{}", default.value.string(p))
+            } else {
+                format!("default: {}", default.value.string(p))
+            };
+            (default.key.clone(), entry)
+        })
+        .collect()
+}
+
+// { title: { type: String, required: true }, count: { type: Number, required: false, default: 0 } }
+fn runtime_props(
+    members: &[TsMember],
+    type_declarations: &[TypeDeclaration],
+    defaults: &[(String, String)],
+) -> String {
+    let mut entries: Vec<String> = Vec::new();
+    for member in members {
+        if let TsMember::Property {
+            key,
+            optional,
+            type_,
+        } = member
+        {
+            let type_ = match ts_types::runtime_types(type_, type_declarations) {
+                Some(types) if types.len() == 1 => types[0].clone(),
+                Some(types) if types.len() > 1 => format!("[{}]", types.join(", ")),
+                _ => String::from("null"),
+            };
+            let default = defaults
+                .iter()
+                .find(|(default_key, _)| default_key == key)
+                .map(|(_, entry)| format!(", {}", entry))
+                .unwrap_or_default();
+            entries.push(format!(
+                "{}: {{ type: {}, required: {}{} }}",
+                object_key(key),
+                type_,
+                !optional,
+                default
+            ));
+        }
+    }
+    format!("{{ {} }}", entries.join(", "))
+}
+
+// ['change', 'update']
+fn runtime_emits(members: &[TsMember], type_declarations: &[TypeDeclaration]) -> String {
+    let mut events: Vec<String> = Vec::new();
+    for member in members {
+        match member {
+            // (e: 'change', value: number): void
+            TsMember::CallSignature(params) => {
+                if let Some(event_type) = params.first() {
+                    for event in ts_types::string_literals(event_type, type_declarations) {
+                        if !events.contains(&event) {
+                            events.push(event);
+                        }
+                    }
+                }
+            }
+            // change: [value: number]
+            TsMember::Property { key, .. } => {
+                if !events.contains(key) {
+                    events.push(key.clone());
+                }
+            }
+        }
+    }
+
+    let events: Vec<String> = events.iter().map(|event| quote(event)).collect();
+    format!("[{}]", events.join(", "))
+}
+
+fn object_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = match chars.next() {
+        Some(c) => is_name_start(c) && chars.all(is_name_part),
+        None => false,
+    };
+    if is_identifier {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut resp = String::from("'");
+    for c in value.chars() {
+        if c == '\'' || c == '\\' {
+            resp.push('\\');
+        }
+        resp.push(c);
+    }
+    resp.push('\'');
+    resp
+}
+
+// The bindings returned by the setup function so they can be used within the template
+fn returned_bindings(p: &Parser, setup: &ScriptSetup) -> Vec<String> {
+    let is_ts = matches!(setup.lang.as_deref(), Some("ts") | Some("tsx"));
+    let mut resp: Vec<String> = Vec::new();
+
+    for import in setup.info.imports.iter() {
+        for binding in import.bindings.iter() {
+            // Typescript imports might be types, returning them would keep an import of something that doesn't exist at runtime
+            // so only return the imports that are used within the template
+            if is_ts && !is_used_in_template(p, binding) {
+                continue;
+            }
+            if !resp.contains(binding) {
+                resp.push(binding.clone());
+            }
+        }
+    }
+    for binding in setup.info.bindings.iter() {
+        if !resp.contains(binding) {
+            resp.push(binding.clone());
+        }
+    }
+
+    resp
+}
+
+// Checks if the name or the kebab case version of it (MyComponent -> my-component) is used within the template
+fn is_used_in_template(p: &Parser, name: &str) -> bool {
+    let template = match p.template.as_ref() {
+        Some(template) => template,
+        None => return false,
+    };
+    let source = template.source.chars(p);
+
+    let mut kebab_case = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if index != 0 {
+                kebab_case.push('-');
+            }
+            kebab_case.push(c.to_ascii_lowercase());
+        } else {
+            kebab_case.push(c);
+        }
+    }
+
+    [name, kebab_case.as_str()].iter().any(|name| {
+        let name: Vec<char> = name.chars().collect();
+        source
+            .windows(name.len())
+            .enumerate()
+            .any(|(index, window)| {
+                let is_word_bound = |c: Option<&char>| match c {
+                    Some(c) => !(c.is_alphanumeric() || *c == '_' || *c == '$'),
+                    None => true,
+                };
+                window == name.as_slice()
+                    && is_word_bound(index.checked_sub(1).and_then(|index| source.get(index)))
+                    && is_word_bound(source.get(index + name.len()))
+            })
+    })
+}
+
+// Writes the normal <script> with `export default` replaced by `const __default__ =`
+fn write_script_without_default_export(
+    p: &Parser,
+    content: &SourceLocation,
+    resp: &mut String,
+) -> Result<(), ParserError> {
+    let (tokens, depths) = tokenize(&p.source_chars, content)
+        .map_err(|err| ParserError::new_at(p, &err.location, err.message))?;
+
+    let default_export = (0..tokens.len()).find(|idx| {
+        depths[*idx] == 0
+            && tokens[*idx].location().eq(p, "export".chars())
+            && tokens
+                .get(idx + 1)
+                .map(|token| token.location().eq(p, "default".chars()))
+                .unwrap_or(false)
+    });
+
+    match default_export {
+        Some(idx) => {
+            SourceLocation(content.0, tokens[idx].start).write_to_string(p, resp);
+            resp.push_str("const __default__ =");
+            SourceLocation(tokens[idx + 1].end, content.1).write_to_string(p, resp);
+        }
+        None => {
+            content.write_to_string(p, resp);
+            resp.push_str("\nconst __default__ = {};");
+        }
+    }
+    Ok(())
+}
+
+// Tokenizes the script, returns the tokens and the bracket depth of every token
+// The opening and closing brackets have the depth of their surroundings
+fn tokenize(
+    chars: &[char],
+    location: &SourceLocation,
+) -> Result<(Vec<Token>, Vec<usize>), JsError> {
+    let mut tokenizer = Tokenizer::new(chars, location);
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut depths: Vec<usize> = Vec::new();
+    // The open brackets, $ is used for the substitution of a template literal (${)
+    let mut brackets: Vec<(char, Token)> = Vec::new();
+
+    loop {
+        let mut token = tokenizer.next_token()?;
+        let mut depth = brackets.len();
+        match token.kind {
+            TokenKind::Eof => break,
+            TokenKind::Template(true) => brackets.push(('$', token)),
+            TokenKind::Punctuator => match tokenizer.text(&token) {
+                ['/'] | ['/', '='] if regex_allowed(chars, tokens.last()) => {
+                    token = tokenizer.rescan_regex(&token)?;
+                }
+                [c @ ('{' | '(' | '[')] => brackets.push((*c, token)),
+                [c @ ('}' | ')' | ']')] => {
+                    let expected_open = match c {
+                        '}' => '{',
+                        ')' => '(',
+                        _ => '[',
+                    };
+                    match brackets.pop() {
+                        Some(('$', _)) if *c == '}' => {
+                            token = tokenizer.rescan_template_continuation(&token)?;
+                            if let TokenKind::Template(true) = token.kind {
+                                brackets.push(('$', token));
+                            }
+                        }
+                        Some((open, _)) if open == expected_open => {}
+                        _ => {
+                            return Err(JsError::new(
+                                token.location(),
+                                format!("unexpected '{}'", c),
+                            ))
+                        }
+                    }
+                    depth = brackets.len();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        tokens.push(token);
+        depths.push(depth);
+    }
+
+    if let Some((open, token)) = brackets.pop() {
+        let open = if open == '$' {
+            "${".to_string()
+        } else {
+            open.to_string()
+        };
+        return Err(JsError::new(
+            token.location(),
+            format!("'{}' is never closed", open),
+        ));
+    }
+
+    Ok((tokens, depths))
+}
+
+// The tokenizer doesn't know if a / is a division or the start of a regex,
+// this is decided based on the previous token
+fn regex_allowed(chars: &[char], previous: Option<&Token>) -> bool {
+    let previous = match previous {
+        Some(previous) => previous,
+        None => return true,
+    };
+    let text = &chars[previous.start..previous.end];
+    match previous.kind {
+        TokenKind::Punctuator => !matches!(text, [')'] | [']'] | ['}'] | ['+', '+'] | ['-', '-']),
+        TokenKind::Template(ends_with_substitution) => ends_with_substitution,
+        TokenKind::Name => {
+            let text: String = text.iter().collect();
+            matches!(
+                text.as_str(),
+                "return"
+                    | "typeof"
+                    | "instanceof"
+                    | "in"
+                    | "of"
+                    | "new"
+                    | "delete"
+                    | "void"
+                    | "throw"
+                    | "case"
+                    | "do"
+                    | "else"
+                    | "yield"
+                    | "await"
+            )
+        }
+        _ => false,
+    }
+}
+
+struct Analyzer<'a> {
+    chars: &'a [char],
+    tokens: Vec<Token>,
+    depths: Vec<usize>,
+    info: ScriptSetupInfo,
+    // Analyzing a normal <script>, these can contain ES module exports
+    normal_script: bool,
+    // The normal <script> exports a default using export { foo as default }
+    default_export_specifier: bool,
+}
+
+impl<'a> Analyzer<'a> {
+    fn text(&self, idx: usize) -> &'a [char] {
+        let token = &self.tokens[idx];
+        &self.chars[token.start..token.end]
+    }
+
+    fn is(&self, idx: usize, value: &str) -> bool {
+        match self.tokens.get(idx) {
+            Some(token) if matches!(token.kind, TokenKind::Name | TokenKind::Punctuator) => {
+                self.text(idx).iter().copied().eq(value.chars())
+            }
+            _ => false,
+        }
+    }
+
+    fn is_name(&self, idx: usize) -> bool {
+        matches!(
+            self.tokens.get(idx),
+            Some(Token {
+                kind: TokenKind::Name,
+                ..
+            })
+        )
+    }
+
+    fn name(&self, idx: usize) -> String {
+        self.text(idx).iter().collect()
+    }
+
+    fn err(&self, idx: usize, message: impl Into<String>) -> JsError {
+        let location = match self.tokens.get(idx) {
+            Some(token) => token.location(),
+            None => match self.tokens.last() {
+                Some(token) => SourceLocation(token.end, token.end),
+                None => SourceLocation(0, 0),
+            },
+        };
+        JsError::new(location, message)
+    }
+
+    // Returns the index of the token that closes the bracket at idx
+    fn closing(&self, idx: usize) -> usize {
+        let depth = self.depths[idx];
+        (idx + 1..self.tokens.len())
+            .find(|idx| self.depths[*idx] == depth)
+            .unwrap_or(self.tokens.len())
+    }
+
+    // Returns the index of the > that closes the < at idx
+    fn closing_angle(&self, idx: usize) -> Option<usize> {
+        let depth = self.depths[idx];
+        let mut open = 0;
+        for idx in idx..self.tokens.len() {
+            if self.depths[idx] < depth {
+                return None;
+            }
+            if self.depths[idx] != depth || self.tokens[idx].kind != TokenKind::Punctuator {
+                continue;
+            }
+            match self.text(idx) {
+                ['<'] => open += 1,
+                ['>'] => open -= 1,
+                ['>', '>'] => open -= 2,
+                ['>', '>', '>'] => open -= 3,
+                [';'] => return None,
+                _ => {}
+            }
+            if open <= 0 {
+                return if open == 0 { Some(idx) } else { None };
+            }
+        }
+        None
+    }
+
+    // Finds the defineProps(..), defineEmits(..) and defineExpose(..) calls
+    fn find_macros(&mut self) -> Result<(), JsError> {
+        for idx in 0..self.tokens.len() {
+            if !self.is_name(idx) || (idx > 0 && (self.is(idx - 1, ".") || self.is(idx - 1, "?.")))
+            {
+                continue;
+            }
+            let name = self.name(idx);
+            if !matches!(
+                name.as_str(),
+                "defineProps" | "defineEmits" | "defineExpose"
+            ) {
+                continue;
+            }
+
+            let mut open_paren = idx + 1;
+            let mut type_arguments = None;
+            if self.is(open_paren, "<") {
+                let closing_angle = match self.closing_angle(open_paren) {
+                    Some(closing_angle) => closing_angle,
+                    None => continue,
+                };
+                type_arguments = Some(SourceLocation(
+                    self.tokens[open_paren].end,
+                    self.tokens[closing_angle].start,
+                ));
+                open_paren = closing_angle + 1;
+            }
+            if !self.is(open_paren, "(") {
+                continue;
+            }
+            let close_paren = self.closing(open_paren);
+
+            let call = MacroCall {
+                location: SourceLocation(self.tokens[idx].start, self.tokens[close_paren].end),
+                name: self.tokens[idx].location(),
+                type_arguments,
+                arguments: SourceLocation(
+                    self.tokens[open_paren].end,
+                    self.tokens[close_paren].start,
+                ),
+            };
+
+            let target = match name.as_str() {
+                "defineProps" => &mut self.info.define_props,
+                "defineEmits" => &mut self.info.define_emits,
+                _ => &mut self.info.define_expose,
+            };
+            if target.is_some() {
+                return Err(self.err(idx, format!("duplicate {}() call", name)));
+            }
+            *target = Some(call);
+        }
+        Ok(())
+    }
+
+    // Finds the top level interfaces and type aliases
+    // Declarations that can't be parsed are ignored, they only cause an error if they are used by defineProps or defineEmits
+    fn find_type_declarations(&mut self) {
+        for idx in 0..self.tokens.len() {
+            if self.depths[idx] != 0 || !self.is_name(idx) || !self.is_statement_start(idx) {
+                continue;
+            }
+            let keyword = if self.is(idx, "export") { idx + 1 } else { idx };
+            let name = keyword + 1;
+            if !self.is_name(name) {
+                continue;
+            }
+
+            let declaration = if self.is(keyword, "interface") {
+                // Skip the type parameters as they might contain a { (interface Foo<T extends { a: string }> { .. })
+                let mut body_search_start = name + 1;
+                if self.is(body_search_start, "<") {
+                    match self.closing_angle(body_search_start) {
+                        Some(closing_angle) => body_search_start = closing_angle + 1,
+                        None => continue,
+                    }
+                }
+                let open = match (body_search_start..self.tokens.len())
+                    .find(|idx| self.depths[*idx] == 0 && self.is(*idx, "{"))
+                {
+                    Some(open) => open,
+                    None => continue,
+                };
+                let close = self.closing(open);
+                if close >= self.tokens.len() {
+                    continue;
+                }
+                ts_types::parse_interface(
+                    self.chars,
+                    &SourceLocation(self.tokens[name].start, self.tokens[close].end),
+                )
+            } else if self.is(keyword, "type") && (self.is(name + 1, "=") || self.is(name + 1, "<"))
+            {
+                let mut end = name + 1;
+                while !self.ends_statement(end, name + 1) {
+                    end += 1;
+                }
+                ts_types::parse_type_alias(
+                    self.chars,
+                    &SourceLocation(self.tokens[name].start, self.tokens[end - 1].end),
+                )
+            } else {
+                continue;
+            };
+
+            if let Ok(declaration) = declaration {
+                self.info.type_declarations.push(declaration);
+            }
+        }
+    }
+
+    // Finds withDefaults(defineProps<..>(), { .. }), the defaults are added to the props generated from the type
+    fn find_with_defaults(&mut self) -> Result<(), JsError> {
+        for idx in 0..self.tokens.len() {
+            if !self.is(idx, "withDefaults")
+                || !self.is(idx + 1, "(")
+                || (idx > 0 && (self.is(idx - 1, ".") || self.is(idx - 1, "?.")))
+            {
+                continue;
+            }
+            if self.info.with_defaults.is_some() {
+                return Err(self.err(idx, "duplicate withDefaults() call"));
+            }
+
+            let open_paren = idx + 1;
+            let close_paren = self.closing(open_paren);
+            let define_props = match (self.info.define_props.as_ref(), self.tokens.get(idx + 2)) {
+                (Some(define_props), Some(token)) if define_props.location.0 == token.start => {
+                    define_props
+                }
+                _ => {
+                    return Err(self.err(
+                        idx,
+                        "withDefaults() expects a defineProps() call as first argument",
+                    ))
+                }
+            };
+            if define_props.type_arguments.is_none() {
+                return Err(self.err(
+                    idx,
+                    "withDefaults() can only be used with a type-based defineProps() declaration",
+                ));
+            }
+
+            // The comma after the defineProps call
+            let comma = (open_paren + 1..close_paren)
+                .find(|idx| self.tokens[*idx].start >= define_props.location.1)
+                .unwrap_or(close_paren);
+            if !self.is(comma, ",") || !self.is(comma + 1, "{") {
+                return Err(self.err(
+                    comma,
+                    "the defaults passed to withDefaults() must be an object literal",
+                ));
+            }
+            let open_brace = comma + 1;
+            let close_brace = self.closing(open_brace);
+            let mut end = close_brace + 1;
+            if self.is(end, ",") {
+                end += 1;
+            }
+            if end != close_paren {
+                return Err(self.err(end, "withDefaults() expects two arguments"));
+            }
+
+            let defaults = self.prop_defaults(open_brace, close_brace)?;
+            self.info.with_defaults = Some(WithDefaults {
+                location: SourceLocation(self.tokens[idx].start, self.tokens[close_paren].end),
+                defaults,
+            });
+        }
+        Ok(())
+    }
+
+    // Returns the properties of the defaults object of withDefaults, only properties with static keys are supported
+    fn prop_defaults(
+        &self,
+        open_brace: usize,
+        close_brace: usize,
+    ) -> Result<Vec<PropDefault>, JsError> {
+        const STATIC_KEYS_ERR: &str =
+            "the defaults passed to withDefaults() can only contain properties with static keys";
+
+        let mut defaults: Vec<PropDefault> = Vec::new();
+        let mut idx = open_brace + 1;
+        while idx < close_brace {
+            let key = match self.tokens[idx].kind {
+                TokenKind::Name => self.name(idx),
+                TokenKind::String => {
+                    let text = self.text(idx);
+                    text[1..text.len() - 1].iter().collect()
+                }
+                _ => return Err(self.err(idx, STATIC_KEYS_ERR)),
+            };
+            let entry_end = (idx + 1..close_brace)
+                .find(|end| self.depths[*end] == self.depths[idx] && self.is(*end, ","))
+                .unwrap_or(close_brace);
+
+            let (value, is_method) = if self.is(idx + 1, ":") && idx + 2 < entry_end {
+                // size: 'small'
+                let value_start = self.tokens[idx + 2].start;
+                (
+                    SourceLocation(value_start, self.tokens[entry_end - 1].end),
+                    false,
+                )
+            } else if self.is(idx + 1, "(") {
+                // items() { return [] }
+                let params_start = self.tokens[idx + 1].start;
+                (
+                    SourceLocation(params_start, self.tokens[entry_end - 1].end),
+                    true,
+                )
+            } else if idx + 1 == entry_end {
+                // { size }
+                (self.tokens[idx].location(), false)
+            } else {
+                return Err(self.err(idx + 1, STATIC_KEYS_ERR));
+            };
+
+            defaults.push(PropDefault {
+                key,
+                value,
+                is_method,
+            });
+            idx = entry_end + 1;
+        }
+        Ok(defaults)
+    }
+
+    // The setup function can't be async, so await can only be used within functions
+    fn find_top_level_await(&self) -> Result<(), JsError> {
+        // For every open bracket around the current token if the bracket is within a function
+        let mut within_function: Vec<bool> = Vec::new();
+        for idx in 0..self.tokens.len() {
+            within_function.truncate(self.depths[idx]);
+            let in_function = |idx: usize| {
+                within_function.last().copied().unwrap_or(false)
+                    || self.in_arrow_function_expression(idx)
+            };
+
+            // foo.await and { await: 1 } are property names
+            let is_await_expression = self.is(idx, "await")
+                && !(idx > 0 && (self.is(idx - 1, ".") || self.is(idx - 1, "?.")))
+                && !self.is(idx + 1, ":");
+            if is_await_expression && !in_function(idx) {
+                return Err(self.err(
+                    idx,
+                    "top level await is not supported in <script setup> as setup() can't be async",
+                ));
+            }
+
+            let opens_bracket = self
+                .depths
+                .get(idx + 1)
+                .is_some_and(|depth| *depth > self.depths[idx]);
+            if opens_bracket {
+                let bracket_in_function = self.is_function_body(idx) || in_function(idx);
+                within_function.push(bracket_in_function);
+            }
+        }
+        Ok(())
+    }
+
+    // Checks if the { at idx is the body of a function, method or arrow function
+    fn is_function_body(&self, idx: usize) -> bool {
+        if idx == 0 || !self.is(idx, "{") {
+            return false;
+        }
+        if self.is(idx - 1, "=>") {
+            return true;
+        }
+        if !self.is(idx - 1, ")") {
+            return false;
+        }
+
+        // The parameters are preceded by the function keyword, the function name or the method name
+        let close_paren = idx - 1;
+        let open_paren = (0..close_paren)
+            .rev()
+            .find(|idx| self.depths[*idx] == self.depths[close_paren] && self.is(*idx, "("));
+        match open_paren {
+            Some(open_paren) if open_paren > 0 => {
+                let before = open_paren - 1;
+                if self.is(before, "*") || self.is(before, "]") {
+                    return true;
+                }
+                self.is_name(before)
+                    && !matches!(
+                        self.name(before).as_str(),
+                        "if" | "for" | "while" | "switch" | "catch" | "with" | "await"
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    // Checks if the token at idx is within the expression body of an arrow function (async () => await foo())
+    fn in_arrow_function_expression(&self, idx: usize) -> bool {
+        let depth = self.depths[idx];
+        let mut current = idx;
+        loop {
+            if current == 0 || (self.depths[current] == depth && self.is_statement_start(current)) {
+                return false;
+            }
+            current -= 1;
+            if self.depths[current] < depth {
+                return false;
+            }
+            if self.depths[current] > depth {
+                continue;
+            }
+            if self.is(current, "=>") {
+                return true;
+            }
+            if self.is(current, ";") || self.is(current, ",") {
+                return false;
+            }
+        }
+    }
+
+    // Finds the imports and the top level declarations
+    fn find_bindings(&mut self) -> Result<(), JsError> {
+        let mut idx = 0;
+        while idx < self.tokens.len() {
+            if self.depths[idx] == 0 && self.is_name(idx) && self.is_statement_start(idx) {
+                if let Some(next) = self.top_level_statement(idx)? {
+                    idx = next;
+                    continue;
+                }
+            }
+            idx += 1;
+        }
+        Ok(())
+    }
+
+    fn top_level_statement(&mut self, idx: usize) -> Result<Option<usize>, JsError> {
+        let resp = match self.name(idx).as_str() {
+            // import('foo') and import.meta are expressions
+            "import" if !self.is(idx + 1, "(") && !self.is(idx + 1, ".") => {
+                Some(self.import_statement(idx)?)
+            }
+            "export" if self.normal_script => {
+                if self.is(idx + 1, "default") {
+                    Some(idx + 2)
+                } else if self.is(idx + 1, "{") {
+                    let close = self.closing(idx + 1);
+                    if (idx + 2..close).any(|idx| self.is(idx, "default")) {
+                        self.default_export_specifier = true;
+                    }
+                    Some(close)
+                } else if self.is_name(idx + 1) {
+                    // export const foo = .., export function foo() {}, ..
+                    self.top_level_statement(idx + 1)?
+                } else {
+                    None
+                }
+            }
+            "export" => {
+                // Typescript types can be exported
+                if self.is(idx + 1, "type") || self.is(idx + 1, "interface") {
+                    None
+                } else {
+                    return Err(self.err(idx, "<script setup> cannot contain ES module exports"));
+                }
+            }
+            "const" | "let" | "var" => self.variable_declarations(idx + 1),
+            "function" => {
+                let mut name = idx + 1;
+                if self.is(name, "*") {
+                    name += 1;
+                }
+                self.add_declared_name(name)
+            }
+            "async" if self.is(idx + 1, "function") && !self.tokens[idx + 1].newline_before => {
+                let mut name = idx + 2;
+                if self.is(name, "*") {
+                    name += 1;
+                }
+                self.add_declared_name(name)
+            }
+            "class" | "enum" => self.add_declared_name(idx + 1),
+            "abstract" if self.is(idx + 1, "class") => self.add_declared_name(idx + 2),
+            _ => None,
+        };
+        Ok(resp)
+    }
+
+    fn add_declared_name(&mut self, idx: usize) -> Option<usize> {
+        if !self.is_name(idx) {
+            return None;
+        }
+        self.info.bindings.push(self.name(idx));
+        Some(idx + 1)
+    }
+
+    // Checks if the token at idx starts a new statement
+    fn is_statement_start(&self, idx: usize) -> bool {
+        if idx == 0 {
+            return true;
+        }
+        if self.is(idx - 1, ";") || self.is(idx - 1, "}") {
+            return true;
+        }
+        self.ends_statement_by_newline(idx)
+    }
+
+    // Automatic semicolon insertion, a line break ends the statement if the next token can't continue it
+    fn ends_statement_by_newline(&self, idx: usize) -> bool {
+        if idx == 0 || !self.tokens[idx].newline_before {
+            return false;
+        }
+
+        let previous_ends_expression = match self.tokens[idx - 1].kind {
+            TokenKind::Punctuator => matches!(
+                self.text(idx - 1),
+                [')'] | [']'] | ['}'] | ['+', '+'] | ['-', '-']
+            ),
+            TokenKind::Template(ends_with_substitution) => !ends_with_substitution,
+            TokenKind::Eof => false,
+            _ => true,
+        };
+        if !previous_ends_expression {
+            return false;
+        }
+
+        match self.tokens[idx].kind {
+            TokenKind::Punctuator => matches!(
+                self.text(idx),
+                ['{'] | ['+', '+'] | ['-', '-'] | ['!'] | ['~'] | [';']
+            ),
+            TokenKind::Template(_) => false,
+            TokenKind::Name => !matches!(
+                self.name(idx).as_str(),
+                "in" | "instanceof" | "as" | "satisfies"
+            ),
+            _ => true,
+        }
+    }
+
+    // Checks if the token at idx ends the statement that started before start
+    fn ends_statement(&self, idx: usize, start: usize) -> bool {
+        idx >= self.tokens.len()
+            || (self.depths[idx] == 0
+                && (self.is(idx, ";") || (idx > start && self.ends_statement_by_newline(idx))))
+    }
+
+    // import foo, { bar, baz as qux, type Foo } from 'foo';
+    fn import_statement(&mut self, start: usize) -> Result<usize, JsError> {
+        let mut idx = start + 1;
+        let mut bindings: Vec<String> = Vec::new();
+
+        // import type Foo from 'foo' or import type { Foo } from 'foo'
+        let type_only = self.is(idx, "type")
+            && (self.is(idx + 1, "{")
+                || self.is(idx + 1, "*")
+                || (self.is_name(idx + 1) && !self.is(idx + 1, "from")));
+        if type_only {
+            idx += 1;
+        }
+
+        loop {
+            match self.tokens.get(idx) {
+                None => return Err(self.err(idx, "expected module specifier")),
+                Some(token) if token.kind == TokenKind::String => {
+                    idx += 1;
+                    break;
+                }
+                _ => {}
+            }
+
+            if self.is(idx, "from")
+                && matches!(
+                    self.tokens.get(idx + 1),
+                    Some(Token {
+                        kind: TokenKind::String,
+                        ..
+                    })
+                )
+            {
+                idx += 2;
+                break;
+            } else if self.is(idx, "*") && self.is(idx + 1, "as") && self.is_name(idx + 2) {
+                bindings.push(self.name(idx + 2));
+                idx += 3;
+            } else if self.is(idx, "{") {
+                let close = self.closing(idx);
+                idx += 1;
+                while idx < close {
+                    // import { type Foo } from 'foo'
+                    let is_type = self.is(idx, "type")
+                        && !self.is(idx + 1, ",")
+                        && !self.is(idx + 1, "}")
+                        && !self.is(idx + 1, "as");
+                    if is_type {
+                        idx += 1;
+                    }
+                    let mut local = idx;
+                    if self.is(idx + 1, "as") {
+                        local = idx + 2;
+                    }
+                    if !self.is_name(local) {
+                        return Err(self.err(local, "expected import name"));
+                    }
+                    if !is_type {
+                        bindings.push(self.name(local));
+                    }
+                    idx = local + 1;
+                    if self.is(idx, ",") {
+                        idx += 1;
+                    }
+                }
+                idx = close + 1;
+            } else if self.is(idx, ",") {
+                idx += 1;
+            } else if self.is_name(idx) {
+                bindings.push(self.name(idx));
+                idx += 1;
+            } else {
+                return Err(self.err(idx, "unexpected token in import statement"));
+            }
+        }
+
+        // Import attributes, import data from './data.json' with { type: 'json' }
+        if (self.is(idx, "with") || self.is(idx, "assert"))
+            && !self.tokens[idx].newline_before
+            && self.is(idx + 1, "{")
+        {
+            idx = self.closing(idx + 1) + 1;
+        }
+        if self.is(idx, ";") {
+            idx += 1;
+        }
+
+        self.info.imports.push(Import {
+            location: SourceLocation(self.tokens[start].start, self.tokens[idx - 1].end),
+            bindings: if type_only { Vec::new() } else { bindings },
+        });
+
+        Ok(idx)
+    }
+
+    // const foo = 1, { bar, baz: [qux] } = obj
+    //       ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+    // Returns None if this is not a declaration, for example when let is used as a name
+    fn variable_declarations(&mut self, start: usize) -> Option<usize> {
+        // Typescript const enum Foo {}
+        if self.is(start, "enum") {
+            return self.add_declared_name(start + 1);
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        let mut idx = start;
+        loop {
+            idx = self.binding_pattern(idx, &mut names)?;
+
+            // Typescript definite assignment and type annotation, let foo!: string
+            if self.is(idx, "!") {
+                idx += 1;
+            }
+            if self.is(idx, ":") {
+                idx = self.skip_type(idx + 1);
+            }
+            if self.is(idx, "=") {
+                idx = self.skip_initializer(idx + 1);
+            }
+            if self.is(idx, ",") && self.depths[idx] == 0 {
+                idx += 1;
+                continue;
+            }
+            break;
+        }
+
+        self.info.bindings.append(&mut names);
+        Some(idx)
+    }
+
+    // Collects the names declared by a binding pattern, returns the index after the pattern
+    fn binding_pattern(&self, idx: usize, names: &mut Vec<String>) -> Option<usize> {
+        if self.is(idx, "{") {
+            let close = self.closing(idx);
+            let mut idx = idx + 1;
+            while idx < close {
+                if self.is(idx, "...") {
+                    idx = self.binding_pattern(idx + 1, names)?;
+                } else if self.is(idx, "[") {
+                    // Computed key, { [key]: value }
+                    idx = self.closing(idx) + 1;
+                    if !self.is(idx, ":") {
+                        return None;
+                    }
+                    idx = self.binding_pattern(idx + 1, names)?;
+                } else if self.is(idx + 1, ":") {
+                    idx = self.binding_pattern(idx + 2, names)?;
+                } else if self.is_name(idx) {
+                    names.push(self.name(idx));
+                    idx += 1;
+                } else {
+                    return None;
+                }
+                idx = self.skip_default_value(idx, close);
+            }
+            Some(close + 1)
+        } else if self.is(idx, "[") {
+            let close = self.closing(idx);
+            let mut idx = idx + 1;
+            while idx < close {
+                if self.is(idx, ",") {
+                    idx += 1;
+                    continue;
+                }
+                if self.is(idx, "...") {
+                    idx += 1;
+                }
+                idx = self.binding_pattern(idx, names)?;
+                idx = self.skip_default_value(idx, close);
+            }
+            Some(close + 1)
+        } else if self.is_name(idx) {
+            names.push(self.name(idx));
+            Some(idx + 1)
+        } else {
+            None
+        }
+    }
+
+    // Skips the default value of an element within a binding pattern including the comma after it
+    fn skip_default_value(&self, mut idx: usize, close: usize) -> usize {
+        if idx >= close {
+            return close;
+        }
+        let depth = self.depths[close] + 1;
+        if self.is(idx, "=") {
+            while idx < close && !(self.depths[idx] == depth && self.is(idx, ",")) {
+                idx += 1;
+            }
+        }
+        if self.is(idx, ",") && idx < close {
+            idx += 1;
+        }
+        idx
+    }
+
+    // Skips a typescript type annotation up to the = or , that follows it
+    fn skip_type(&self, start: usize) -> usize {
+        let mut idx = start;
+        let mut open_angles = 0;
+        while !self.ends_statement(idx, start) {
+            if self.depths[idx] == 0 {
+                match self.text(idx) {
+                    ['<'] => open_angles += 1,
+                    ['>'] => open_angles -= 1,
+                    ['>', '>'] => open_angles -= 2,
+                    ['>', '>', '>'] => open_angles -= 3,
+                    ['='] | [','] if open_angles <= 0 => return idx,
+                    _ => {}
+                }
+            }
+            idx += 1;
+        }
+        idx
+    }
+
+    // Skips the value of a declaration up to the , that separates it from the next declaration
+    fn skip_initializer(&self, start: usize) -> usize {
+        let mut idx = start;
+        while !self.ends_statement(idx, start) {
+            // A comma might also be part of typescript type arguments, new Map<string, number>()
+            if self.depths[idx] == 0 && self.is(idx, ",") && self.declaration_follows(idx + 1) {
+                return idx;
+            }
+            idx += 1;
+        }
+        idx
+    }
+
+    fn declaration_follows(&self, idx: usize) -> bool {
+        if self.is(idx, "{") || self.is(idx, "[") {
+            return true;
+        }
+        self.is_name(idx)
+            && (idx + 1 >= self.tokens.len()
+                || self.is(idx + 1, "=")
+                || self.is(idx + 1, ":")
+                || self.is(idx + 1, ",")
+                || self.is(idx + 1, ";")
+                || self.is(idx + 1, "!")
+                || self.tokens[idx + 1].newline_before)
+    }
+}
@@ -0,0 +1,441 @@
+use super::super::SourceLocation;
+
+/*
+    A javascript tokenizer used by the template expression parser
+
+    The tokenizer does not know if a / is the start of a regex or a division,
+    the parser decides this and re-scans the token using `Tokenizer::rescan_regex`.
+    The same goes for template literals, the parser continues a template literal
+    after a substitution (`${ .. }`) using `Tokenizer::rescan_template_continuation`.
+*/
+
+#[derive(Debug, Clone)]
+pub struct JsError {
+    pub message: String,
+    pub location: SourceLocation,
+}
+
+impl JsError {
+    pub fn new(location: SourceLocation, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            location,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Name,
+    PrivateName, // #foo
+    Number,
+    String,
+    // A template literal or a part of it, the bool is true if the part ends with a substitution (${)
+    Template(bool),
+    Regex,
+    Punctuator,
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+    // There is a line break between the previous token and this token, used for automatic semicolon insertion
+    pub newline_before: bool,
+}
+
+impl Token {
+    pub fn location(&self) -> SourceLocation {
+        SourceLocation(self.start, self.end)
+    }
+}
+
+// Sorted so that the longest punctuators are matched first
+const PUNCTUATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "**",
+    "<<", ">>", "{", "}", "(", ")", "[", "]", ";", ",", "<", ">", "+", "-", "*", "/", "%", "&",
+    "|", "^", "!", "~", "?", ":", "=", ".",
+];
+
+pub struct Tokenizer<'a> {
+    chars: &'a [char],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(chars: &'a [char], location: &SourceLocation) -> Self {
+        Self {
+            chars,
+            pos: location.0,
+            end: location.1,
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn set_pos(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    pub fn text(&self, token: &Token) -> &'a [char] {
+        &self.chars[token.start..token.end]
+    }
+
+    fn seek(&self, offset: usize) -> Option<char> {
+        let idx = self.pos + offset;
+        if idx < self.end {
+            Some(self.chars[idx])
+        } else {
+            None
+        }
+    }
+
+    fn next_is(&self, value: &str) -> bool {
+        let mut chars = value.chars();
+        let len = chars.clone().count();
+        self.pos + len <= self.end
+            && self.chars[self.pos..self.pos + len]
+                .iter()
+                .all(|c| chars.next() == Some(*c))
+    }
+
+    fn err_here(&self, message: impl Into<String>) -> JsError {
+        let end = (self.pos + 1).min(self.end).max(self.pos);
+        JsError::new(SourceLocation(self.pos, end), message)
+    }
+
+    // Skips spaces and comments, returns true if a line break was found
+    fn skip_spacing(&mut self) -> Result<bool, JsError> {
+        let mut newline = false;
+        while let Some(c) = self.seek(0) {
+            match c {
+                '\n' | '\r' | '\u{2028}' | '\u{2029}' => {
+                    newline = true;
+                    self.pos += 1;
+                }
+                '/' if self.seek(1) == Some('/') => {
+                    while let Some(c) = self.seek(0) {
+                        if is_line_terminator(c) {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                '/' if self.seek(1) == Some('*') => {
+                    let start = self.pos;
+                    self.pos += 2;
+                    loop {
+                        match self.seek(0) {
+                            None => {
+                                self.pos = start;
+                                return Err(self.err_here("unterminated comment"));
+                            }
+                            Some('*') if self.seek(1) == Some('/') => {
+                                self.pos += 2;
+                                break;
+                            }
+                            Some(c) => {
+                                if is_line_terminator(c) {
+                                    newline = true;
+                                }
+                                self.pos += 1;
+                            }
+                        }
+                    }
+                }
+                c if c.is_whitespace() || c == '\u{feff}' => self.pos += 1,
+                _ => break,
+            }
+        }
+        Ok(newline)
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, JsError> {
+        let newline_before = self.skip_spacing()?;
+        let start = self.pos;
+        let kind = match self.seek(0) {
+            None => TokenKind::Eof,
+            Some(c) if is_name_start(c) || c == '\\' => {
+                self.read_name()?;
+                TokenKind::Name
+            }
+            Some('#') if self.seek(1).map(is_name_start).unwrap_or(false) => {
+                self.pos += 1;
+                self.read_name()?;
+                TokenKind::PrivateName
+            }
+            Some(c) if c.is_ascii_digit() => {
+                self.read_number()?;
+                TokenKind::Number
+            }
+            Some('.') if self.seek(1).map(|c| c.is_ascii_digit()).unwrap_or(false) => {
+                self.read_number()?;
+                TokenKind::Number
+            }
+            Some(c) if c == '"' || c == '\'' => {
+                self.read_string(c)?;
+                TokenKind::String
+            }
+            Some('`') => {
+                self.pos += 1;
+                TokenKind::Template(self.read_template()?)
+            }
+            Some(c) => {
+                // ?. followed by a digit is a conditional followed by a number (a?.5:b)
+                let is_optional_chain = self.next_is("?.")
+                    && !self.seek(2).map(|c| c.is_ascii_digit()).unwrap_or(false);
+                if is_optional_chain {
+                    self.pos += 2;
+                } else if let Some(punctuator) = PUNCTUATORS.iter().find(|v| self.next_is(v)) {
+                    self.pos += punctuator.len();
+                } else {
+                    return Err(self.err_here(format!("unexpected character '{}'", c)));
+                }
+                TokenKind::Punctuator
+            }
+        };
+
+        Ok(Token {
+            kind,
+            start,
+            end: self.pos,
+            newline_before,
+        })
+    }
+
+    fn read_name(&mut self) -> Result<(), JsError> {
+        let mut first = true;
+        while let Some(c) = self.seek(0) {
+            if c == '\\' {
+                // Unicode escape sequence (\u0061 or \u{61})
+                if self.seek(1) != Some('u') {
+                    return Err(self.err_here("invalid escape sequence in identifier"));
+                }
+                self.pos += 2;
+                self.read_unicode_escape()?;
+            } else if (first && is_name_start(c)) || (!first && is_name_part(c)) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+            first = false;
+        }
+        Ok(())
+    }
+
+    fn read_unicode_escape(&mut self) -> Result<(), JsError> {
+        if self.seek(0) == Some('{') {
+            self.pos += 1;
+            let mut digits = 0;
+            while let Some(c) = self.seek(0) {
+                if c == '}' {
+                    break;
+                }
+                if !c.is_ascii_hexdigit() {
+                    return Err(self.err_here("invalid unicode escape sequence"));
+                }
+                digits += 1;
+                self.pos += 1;
+            }
+            if digits == 0 || self.seek(0) != Some('}') {
+                return Err(self.err_here("invalid unicode escape sequence"));
+            }
+            self.pos += 1;
+        } else {
+            for _ in 0..4 {
+                match self.seek(0) {
+                    Some(c) if c.is_ascii_hexdigit() => self.pos += 1,
+                    _ => return Err(self.err_here("invalid unicode escape sequence")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_digits(&mut self, is_digit: fn(char) -> bool) -> Result<usize, JsError> {
+        let mut count = 0;
+        let mut last_was_separator = false;
+        while let Some(c) = self.seek(0) {
+            if c == '_' {
+                if count == 0 || last_was_separator {
+                    return Err(self.err_here("invalid numeric separator"));
+                }
+                last_was_separator = true;
+            } else if is_digit(c) {
+                last_was_separator = false;
+                count += 1;
+            } else {
+                break;
+            }
+            self.pos += 1;
+        }
+        if last_was_separator {
+            return Err(self.err_here("invalid numeric separator"));
+        }
+        Ok(count)
+    }
+
+    fn read_number(&mut self) -> Result<(), JsError> {
+        let radix_digit: Option<fn(char) -> bool> = match (self.seek(0), self.seek(1)) {
+            (Some('0'), Some('x' | 'X')) => Some(|c| c.is_ascii_hexdigit()),
+            (Some('0'), Some('o' | 'O')) => Some(|c| ('0'..='7').contains(&c)),
+            (Some('0'), Some('b' | 'B')) => Some(|c| c == '0' || c == '1'),
+            _ => None,
+        };
+
+        if let Some(is_digit) = radix_digit {
+            self.pos += 2;
+            if self.read_digits(is_digit)? == 0 {
+                return Err(self.err_here("expected digits"));
+            }
+            if self.seek(0) == Some('n') {
+                self.pos += 1;
+            }
+        } else {
+            let integer_digits = self.read_digits(|c| c.is_ascii_digit())?;
+            let mut is_integer = true;
+            if self.seek(0) == Some('.') {
+                is_integer = false;
+                self.pos += 1;
+                let fraction_digits = self.read_digits(|c| c.is_ascii_digit())?;
+                if integer_digits == 0 && fraction_digits == 0 {
+                    return Err(self.err_here("expected digits"));
+                }
+            }
+            if let Some('e' | 'E') = self.seek(0) {
+                is_integer = false;
+                self.pos += 1;
+                if let Some('+' | '-') = self.seek(0) {
+                    self.pos += 1;
+                }
+                if self.read_digits(|c| c.is_ascii_digit())? == 0 {
+                    return Err(self.err_here("expected exponent digits"));
+                }
+            }
+            if is_integer && self.seek(0) == Some('n') {
+                self.pos += 1;
+            }
+        }
+
+        match self.seek(0) {
+            Some(c) if is_name_start(c) || c.is_ascii_digit() => {
+                Err(self.err_here("identifier directly after number"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn read_string(&mut self, quote: char) -> Result<(), JsError> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.seek(0) {
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some('\\') => self.pos += 2,
+                Some('\n' | '\r') | None => {
+                    self.pos = start;
+                    return Err(self.err_here("unterminated string"));
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    // Reads the remainder of a template literal part, returns true if the part ends with a substitution (${)
+    fn read_template(&mut self) -> Result<bool, JsError> {
+        let start = self.pos;
+        loop {
+            match self.seek(0) {
+                Some('`') => {
+                    self.pos += 1;
+                    return Ok(false);
+                }
+                Some('$') if self.seek(1) == Some('{') => {
+                    self.pos += 2;
+                    return Ok(true);
+                }
+                Some('\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => {
+                    self.pos = start.saturating_sub(1);
+                    return Err(self.err_here("unterminated template literal"));
+                }
+            }
+        }
+    }
+
+    // Continues a template literal after a substitution, token is expected to be the } closing the substitution
+    pub fn rescan_template_continuation(&mut self, token: &Token) -> Result<Token, JsError> {
+        self.pos = token.start + 1;
+        let ends_with_substitution = self.read_template()?;
+        Ok(Token {
+            kind: TokenKind::Template(ends_with_substitution),
+            start: token.start,
+            end: self.pos,
+            newline_before: token.newline_before,
+        })
+    }
+
+    // Re-scans a / or /= token as a regular expression literal
+    pub fn rescan_regex(&mut self, token: &Token) -> Result<Token, JsError> {
+        self.pos = token.start + 1;
+        let mut in_class = false;
+        loop {
+            match self.seek(0) {
+                Some('\\') => self.pos += 2,
+                Some('[') => {
+                    in_class = true;
+                    self.pos += 1;
+                }
+                Some(']') => {
+                    in_class = false;
+                    self.pos += 1;
+                }
+                Some('/') if !in_class => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) if !is_line_terminator(c) => self.pos += 1,
+                _ => {
+                    self.pos = token.start;
+                    return Err(self.err_here("unterminated regular expression"));
+                }
+            }
+        }
+        // The flags
+        while let Some(c) = self.seek(0) {
+            if !is_name_part(c) {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        Ok(Token {
+            kind: TokenKind::Regex,
+            start: token.start,
+            end: self.pos,
+            newline_before: token.newline_before,
+        })
+    }
+}
+
+pub fn is_name_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+pub fn is_name_part(c: char) -> bool {
+    is_name_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
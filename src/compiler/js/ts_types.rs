@@ -0,0 +1,805 @@
+use super::super::SourceLocation;
+use super::tokenizer::{JsError, Token, TokenKind, Tokenizer};
+
+/*
+    A parser for the subset of typescript types needed to generate runtime props and emits declarations
+
+    defineProps<{ title: string; count?: number }>()
+    Becomes:
+    { title: { type: String, required: true }, count: { type: Number, required: false } }
+
+    defineEmits<{ (e: 'change', value: number): void }>()
+    Becomes:
+    ['change']
+
+    Types that can't be converted into a runtime type (generics, conditional types, ..) are parsed as Unknown
+*/
+
+#[derive(Debug, Clone)]
+pub enum TsType {
+    // string, number, boolean, any, null, ..
+    Keyword(String),
+    // 'foo', the value is without the quotes
+    StringLiteral(String),
+    NumberLiteral,
+    BooleanLiteral,
+    TemplateLiteral,
+    TypeLiteral(Vec<TsMember>),
+    // Foo or Foo.Bar, type arguments are ignored
+    Reference(String),
+    // string[] or [string, number]
+    Array,
+    // (a: string, b: number) => void, contains the types of the parameters
+    Function(Vec<TsType>),
+    Union(Vec<TsType>),
+    Intersection(Vec<TsType>),
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub enum TsMember {
+    // foo?: string, methods (foo(): void) are properties with a function type
+    Property {
+        key: String,
+        optional: bool,
+        type_: TsType,
+    },
+    // (e: 'change', value: number): void, contains the types of the parameters
+    CallSignature(Vec<TsType>),
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeDeclaration {
+    // interface Foo extends Bar { .. }
+    Interface {
+        name: String,
+        extends: Vec<TsType>,
+        members: Vec<TsMember>,
+    },
+    // type Foo = ..
+    Alias {
+        name: String,
+        type_: TsType,
+    },
+}
+
+impl TypeDeclaration {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Interface { name, .. } | Self::Alias { name, .. } => name,
+        }
+    }
+}
+
+// Parses a type, for example the type arguments of defineProps<..>()
+pub fn parse_type(chars: &[char], location: &SourceLocation) -> Result<TsType, JsError> {
+    let mut parser = TsParser::new(chars, location)?;
+    let resp = parser.parse_type()?;
+    parser.expect_end()?;
+    Ok(resp)
+}
+
+// Parses an interface declaration starting at the name: Foo<T> extends Bar { .. }
+pub fn parse_interface(
+    chars: &[char],
+    location: &SourceLocation,
+) -> Result<TypeDeclaration, JsError> {
+    let mut parser = TsParser::new(chars, location)?;
+    let name = parser.expect_name()?;
+    parser.skip_type_parameters()?;
+
+    let mut extends: Vec<TsType> = Vec::new();
+    if parser.eat("extends")? {
+        loop {
+            extends.push(parser.parse_reference()?);
+            if !parser.eat(",")? {
+                break;
+            }
+        }
+    }
+
+    let members = parser.parse_members()?;
+    parser.expect_end()?;
+    Ok(TypeDeclaration::Interface {
+        name,
+        extends,
+        members,
+    })
+}
+
+// Parses a type alias declaration starting at the name: Foo<T> = ..
+pub fn parse_type_alias(
+    chars: &[char],
+    location: &SourceLocation,
+) -> Result<TypeDeclaration, JsError> {
+    let mut parser = TsParser::new(chars, location)?;
+    let name = parser.expect_name()?;
+    parser.skip_type_parameters()?;
+    parser.expect("=")?;
+    let type_ = parser.parse_type()?;
+    parser.eat(";")?;
+    parser.expect_end()?;
+    Ok(TypeDeclaration::Alias { name, type_ })
+}
+
+// Limits how deep type references are followed, protects against recursive types (type Foo = Foo[] | Bar)
+const MAX_RESOLVE_DEPTH: usize = 32;
+
+fn find_declaration<'a>(
+    name: &str,
+    declarations: &'a [TypeDeclaration],
+) -> Option<&'a TypeDeclaration> {
+    declarations
+        .iter()
+        .find(|declaration| declaration.name() == name)
+}
+
+// Resolves the members of the type passed to defineProps<..>() or defineEmits<..>()
+pub fn resolve_members(type_: &TsType, declarations: &[TypeDeclaration]) -> Option<Vec<TsMember>> {
+    resolve_members_with_depth(type_, declarations, 0)
+}
+
+fn resolve_members_with_depth(
+    type_: &TsType,
+    declarations: &[TypeDeclaration],
+    depth: usize,
+) -> Option<Vec<TsMember>> {
+    if depth > MAX_RESOLVE_DEPTH {
+        return None;
+    }
+
+    match type_ {
+        TsType::TypeLiteral(members) => Some(members.clone()),
+        TsType::Function(params) => Some(vec![TsMember::CallSignature(params.clone())]),
+        TsType::Reference(name) => match find_declaration(name, declarations)? {
+            TypeDeclaration::Interface {
+                extends, members, ..
+            } => {
+                let mut resp: Vec<TsMember> = Vec::new();
+                for extended in extends {
+                    resp.append(&mut resolve_members_with_depth(
+                        extended,
+                        declarations,
+                        depth + 1,
+                    )?);
+                }
+                resp.extend(members.iter().cloned());
+                Some(resp)
+            }
+            TypeDeclaration::Alias { type_, .. } => {
+                resolve_members_with_depth(type_, declarations, depth + 1)
+            }
+        },
+        // { a: string } & { b: number } and for emits ((e: 'a') => void) | ((e: 'b') => void)
+        TsType::Intersection(types) | TsType::Union(types) => {
+            let mut resp: Vec<TsMember> = Vec::new();
+            for type_ in types {
+                resp.append(&mut resolve_members_with_depth(
+                    type_,
+                    declarations,
+                    depth + 1,
+                )?);
+            }
+            Some(resp)
+        }
+        _ => None,
+    }
+}
+
+// Returns the runtime constructors of a type (string | number -> [String, Number])
+// None is returned if the runtime type is unknown, in that case the prop accepts any type
+pub fn runtime_types(type_: &TsType, declarations: &[TypeDeclaration]) -> Option<Vec<String>> {
+    let mut resp: Vec<String> = Vec::new();
+    add_runtime_types(type_, declarations, 0, &mut resp)?;
+    Some(resp)
+}
+
+fn add_runtime_types(
+    type_: &TsType,
+    declarations: &[TypeDeclaration],
+    depth: usize,
+    resp: &mut Vec<String>,
+) -> Option<()> {
+    if depth > MAX_RESOLVE_DEPTH {
+        return None;
+    }
+
+    let runtime_type = match type_ {
+        TsType::Keyword(keyword) => match keyword.as_str() {
+            "string" => "String",
+            "number" => "Number",
+            "boolean" => "Boolean",
+            "bigint" => "BigInt",
+            "symbol" => "Symbol",
+            "object" => "Object",
+            "null" => "null",
+            // These have no runtime constructor
+            "undefined" | "void" | "never" => return Some(()),
+            _ => return None,
+        },
+        TsType::StringLiteral(_) | TsType::TemplateLiteral => "String",
+        TsType::NumberLiteral => "Number",
+        TsType::BooleanLiteral => "Boolean",
+        TsType::TypeLiteral(members) => {
+            let only_call_signatures = !members.is_empty()
+                && members
+                    .iter()
+                    .all(|member| matches!(member, TsMember::CallSignature(_)));
+            if only_call_signatures {
+                "Function"
+            } else {
+                "Object"
+            }
+        }
+        TsType::Array => "Array",
+        TsType::Function(_) => "Function",
+        TsType::Intersection(_) => "Object",
+        TsType::Union(types) => {
+            for type_ in types {
+                add_runtime_types(type_, declarations, depth + 1, resp)?;
+            }
+            return Some(());
+        }
+        TsType::Reference(name) => match find_declaration(name, declarations) {
+            Some(TypeDeclaration::Interface { .. }) => "Object",
+            Some(TypeDeclaration::Alias { type_, .. }) => {
+                return add_runtime_types(type_, declarations, depth + 1, resp);
+            }
+            None => match name.as_str() {
+                "Array" | "ReadonlyArray" => "Array",
+                "Function" => "Function",
+                "Object" | "Record" | "Partial" | "Required" | "Readonly" | "Pick" | "Omit" => {
+                    "Object"
+                }
+                "String" | "Number" | "Boolean" | "Symbol" | "BigInt" | "Date" | "RegExp"
+                | "Error" | "Promise" | "Map" | "Set" | "WeakMap" | "WeakSet" => name,
+                _ => return None,
+            },
+        },
+        TsType::Unknown => return None,
+    };
+
+    if !resp.iter().any(|existing| existing == runtime_type) {
+        resp.push(runtime_type.to_string());
+    }
+    Some(())
+}
+
+// Returns the string literals of a type ('a' | 'b' -> [a, b]), used for the event names of defineEmits
+pub fn string_literals(type_: &TsType, declarations: &[TypeDeclaration]) -> Vec<String> {
+    let mut resp: Vec<String> = Vec::new();
+    add_string_literals(type_, declarations, 0, &mut resp);
+    resp
+}
+
+fn add_string_literals(
+    type_: &TsType,
+    declarations: &[TypeDeclaration],
+    depth: usize,
+    resp: &mut Vec<String>,
+) {
+    if depth > MAX_RESOLVE_DEPTH {
+        return;
+    }
+    match type_ {
+        TsType::StringLiteral(value) => resp.push(value.clone()),
+        TsType::Union(types) => {
+            for type_ in types {
+                add_string_literals(type_, declarations, depth + 1, resp);
+            }
+        }
+        TsType::Reference(name) => {
+            if let Some(TypeDeclaration::Alias { type_, .. }) = find_declaration(name, declarations)
+            {
+                add_string_literals(type_, declarations, depth + 1, resp);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct TsParser<'a> {
+    tokenizer: Tokenizer<'a>,
+    token: Token,
+}
+
+impl<'a> TsParser<'a> {
+    fn new(chars: &'a [char], location: &SourceLocation) -> Result<Self, JsError> {
+        let mut tokenizer = Tokenizer::new(chars, location);
+        let token = tokenizer.next_token()?;
+        Ok(Self { tokenizer, token })
+    }
+
+    //
+    // Token helpers
+    //
+
+    fn next(&mut self) -> Result<Token, JsError> {
+        let current = self.token;
+        self.token = self.tokenizer.next_token()?;
+        Ok(current)
+    }
+
+    fn peek(&mut self) -> Result<Token, JsError> {
+        let pos = self.tokenizer.pos();
+        let token = self.tokenizer.next_token();
+        self.tokenizer.set_pos(pos);
+        token
+    }
+
+    fn next_is(&mut self, value: &str) -> Result<bool, JsError> {
+        let next = self.peek()?;
+        Ok(self.token_is(&next, value))
+    }
+
+    fn text(&self, token: &Token) -> String {
+        self.tokenizer.text(token).iter().collect()
+    }
+
+    fn token_is(&self, token: &Token, value: &str) -> bool {
+        matches!(token.kind, TokenKind::Name | TokenKind::Punctuator)
+            && self.tokenizer.text(token).iter().copied().eq(value.chars())
+    }
+
+    fn is(&self, value: &str) -> bool {
+        self.token_is(&self.token, value)
+    }
+
+    fn eat(&mut self, value: &str) -> Result<bool, JsError> {
+        if self.is(value) {
+            self.next()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn unexpected(&self) -> JsError {
+        if self.token.kind == TokenKind::Eof {
+            JsError::new(self.token.location(), "unexpected end of type")
+        } else {
+            JsError::new(
+                self.token.location(),
+                format!("unexpected '{}' in type", self.text(&self.token)),
+            )
+        }
+    }
+
+    fn expect(&mut self, value: &str) -> Result<Token, JsError> {
+        if self.is(value) {
+            self.next()
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_name(&mut self) -> Result<String, JsError> {
+        if self.token.kind == TokenKind::Name {
+            let token = self.next()?;
+            Ok(self.text(&token))
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), JsError> {
+        if self.token.kind == TokenKind::Eof {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    // The tokenizer reads >> as one token, within types it can also be two closing angle brackets (Array<Array<string>>)
+    fn expect_closing_angle(&mut self) -> Result<(), JsError> {
+        match self.tokenizer.text(&self.token) {
+            ['>'] => {
+                self.next()?;
+            }
+            ['>', ..] if self.token.kind == TokenKind::Punctuator => {
+                self.tokenizer.set_pos(self.token.start + 1);
+                self.token = self.tokenizer.next_token()?;
+            }
+            _ => return Err(self.unexpected()),
+        }
+        Ok(())
+    }
+
+    // Skips tokens up to and including the closing bracket, expects the opening bracket to be consumed already
+    fn skip_brackets(&mut self, close: &str) -> Result<(), JsError> {
+        loop {
+            if self.token.kind == TokenKind::Eof {
+                return Err(self.unexpected());
+            }
+            if self.is(close) {
+                self.next()?;
+                return Ok(());
+            }
+            if self.eat("(")? {
+                self.skip_brackets(")")?;
+            } else if self.eat("[")? {
+                self.skip_brackets("]")?;
+            } else if self.eat("{")? {
+                self.skip_brackets("}")?;
+            } else {
+                self.next()?;
+            }
+        }
+    }
+
+    // <T, U extends string = 'a'>
+    fn skip_type_parameters(&mut self) -> Result<(), JsError> {
+        if self.eat("<")? {
+            self.parse_type_arguments_rest()?;
+        }
+        Ok(())
+    }
+
+    // Parses the types within < > ignoring them, expects the < to be consumed already
+    fn parse_type_arguments_rest(&mut self) -> Result<(), JsError> {
+        loop {
+            // Type parameters might have a constraint and default (T extends string = 'a')
+            self.parse_type()?;
+            if self.eat("extends")? {
+                self.parse_type()?;
+            }
+            if self.eat("=")? {
+                self.parse_type()?;
+            }
+            if !self.eat(",")? {
+                break;
+            }
+        }
+        self.expect_closing_angle()
+    }
+
+    //
+    // Types
+    //
+
+    fn parse_type(&mut self) -> Result<TsType, JsError> {
+        // Generic functions, <T>(value: T) => void
+        if self.is("<") {
+            self.next()?;
+            self.parse_type_arguments_rest()?;
+            return self.parse_type();
+        }
+
+        let check_type = self.parse_union()?;
+
+        // Conditional types, T extends string ? A : B
+        if self.is("extends") && !self.token.newline_before {
+            self.next()?;
+            self.parse_union()?;
+            self.expect("?")?;
+            self.parse_type()?;
+            self.expect(":")?;
+            self.parse_type()?;
+            return Ok(TsType::Unknown);
+        }
+
+        Ok(check_type)
+    }
+
+    fn parse_union(&mut self) -> Result<TsType, JsError> {
+        self.eat("|")?;
+        let mut types = vec![self.parse_intersection()?];
+        while self.eat("|")? {
+            types.push(self.parse_intersection()?);
+        }
+        Ok(if types.len() == 1 {
+            types.remove(0)
+        } else {
+            TsType::Union(types)
+        })
+    }
+
+    fn parse_intersection(&mut self) -> Result<TsType, JsError> {
+        self.eat("&")?;
+        let mut types = vec![self.parse_postfix()?];
+        while self.eat("&")? {
+            types.push(self.parse_postfix()?);
+        }
+        Ok(if types.len() == 1 {
+            types.remove(0)
+        } else {
+            TsType::Intersection(types)
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<TsType, JsError> {
+        let mut type_ = self.parse_primary()?;
+        while self.is("[") && !self.token.newline_before {
+            self.next()?;
+            if self.eat("]")? {
+                type_ = TsType::Array;
+            } else {
+                // Indexed access, Foo['bar']
+                self.parse_type()?;
+                self.expect("]")?;
+                type_ = TsType::Unknown;
+            }
+        }
+        Ok(type_)
+    }
+
+    fn parse_primary(&mut self) -> Result<TsType, JsError> {
+        match self.token.kind {
+            TokenKind::String => {
+                let token = self.next()?;
+                let text = self.text(&token);
+                Ok(TsType::StringLiteral(text[1..text.len() - 1].to_string()))
+            }
+            TokenKind::Number => {
+                self.next()?;
+                Ok(TsType::NumberLiteral)
+            }
+            TokenKind::Template(mut ends_with_substitution) => {
+                self.next()?;
+                while ends_with_substitution {
+                    self.parse_type()?;
+                    if !self.is("}") {
+                        return Err(self.unexpected());
+                    }
+                    self.token = self.tokenizer.rescan_template_continuation(&self.token)?;
+                    ends_with_substitution = matches!(self.token.kind, TokenKind::Template(true));
+                    self.next()?;
+                }
+                Ok(TsType::TemplateLiteral)
+            }
+            TokenKind::Punctuator => self.parse_punctuator_type(),
+            TokenKind::Name => self.parse_name_type(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_punctuator_type(&mut self) -> Result<TsType, JsError> {
+        if self.eat("-")? {
+            // Negative number, -1
+            if self.token.kind != TokenKind::Number {
+                return Err(self.unexpected());
+            }
+            self.next()?;
+            Ok(TsType::NumberLiteral)
+        } else if self.is("{") {
+            // Mapped types, { [K in Keys]: T }
+            let is_mapped_type = {
+                let pos = self.tokenizer.pos();
+                let mut tokens = Vec::new();
+                for _ in 0..3 {
+                    tokens.push(self.tokenizer.next_token()?);
+                }
+                self.tokenizer.set_pos(pos);
+                (self.token_is(&tokens[0], "[") && self.token_is(&tokens[2], "in"))
+                    || (self.token_is(&tokens[0], "readonly") && self.token_is(&tokens[1], "["))
+                    || (self.token_is(&tokens[0], "+") || self.token_is(&tokens[0], "-"))
+            };
+            if is_mapped_type {
+                self.next()?;
+                self.skip_brackets("}")?;
+                return Ok(TsType::Keyword(String::from("object")));
+            }
+            Ok(TsType::TypeLiteral(self.parse_members()?))
+        } else if self.eat("[")? {
+            // Tuple, [string, number]
+            self.skip_brackets("]")?;
+            Ok(TsType::Array)
+        } else if self.is("(") {
+            if self.is_function_type()? {
+                self.parse_function_type()
+            } else {
+                self.next()?;
+                let type_ = self.parse_type()?;
+                self.expect(")")?;
+                Ok(type_)
+            }
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn parse_name_type(&mut self) -> Result<TsType, JsError> {
+        let name = self.text(&self.token);
+        match name.as_str() {
+            "true" | "false" => {
+                self.next()?;
+                Ok(TsType::BooleanLiteral)
+            }
+            "string" | "number" | "boolean" | "bigint" | "symbol" | "object" | "any"
+            | "unknown" | "never" | "void" | "undefined" | "null" => {
+                self.next()?;
+                Ok(TsType::Keyword(name))
+            }
+            "typeof" => {
+                self.next()?;
+                self.parse_reference()?;
+                Ok(TsType::Unknown)
+            }
+            "keyof" | "readonly" | "unique" => {
+                self.next()?;
+                let type_ = self.parse_postfix()?;
+                Ok(if name == "readonly" {
+                    type_
+                } else {
+                    TsType::Unknown
+                })
+            }
+            "infer" => {
+                self.next()?;
+                self.expect_name()?;
+                Ok(TsType::Unknown)
+            }
+            "new" | "abstract" => {
+                // Constructor types, new () => Foo
+                self.next()?;
+                self.eat("new")?;
+                self.parse_function_type()?;
+                Ok(TsType::Keyword(String::from("object")))
+            }
+            _ => self.parse_reference(),
+        }
+    }
+
+    // Foo, Foo.Bar or Foo<T>
+    fn parse_reference(&mut self) -> Result<TsType, JsError> {
+        let mut name = self.expect_name()?;
+        while self.is(".") {
+            self.next()?;
+            name.push('.');
+            name.push_str(&self.expect_name()?);
+        }
+        if self.is("<") && !self.token.newline_before {
+            self.next()?;
+            self.parse_type_arguments_rest()?;
+        }
+        Ok(TsType::Reference(name))
+    }
+
+    // Checks if the ( starts a function type, (a: string) => void, or is a parenthesized type, (string | number)[]
+    fn is_function_type(&mut self) -> Result<bool, JsError> {
+        let next = self.peek()?;
+        if self.token_is(&next, ")") || self.token_is(&next, "...") {
+            return Ok(true);
+        }
+        if next.kind != TokenKind::Name {
+            return Ok(false);
+        }
+
+        let pos = self.tokenizer.pos();
+        self.tokenizer.set_pos(next.end);
+        let after_name = self.tokenizer.next_token();
+        self.tokenizer.set_pos(pos);
+        let after_name = after_name?;
+        Ok([":", ",", "?", ")", "="]
+            .iter()
+            .any(|value| self.token_is(&after_name, value)))
+    }
+
+    // (a: string, b?: number) => void
+    fn parse_function_type(&mut self) -> Result<TsType, JsError> {
+        let params = self.parse_params()?;
+        self.expect("=>")?;
+        // Type predicates, (value: unknown) => value is string
+        let next = self.peek()?;
+        if self.token.kind == TokenKind::Name && self.token_is(&next, "is") {
+            self.next()?;
+            self.next()?;
+        }
+        self.parse_type()?;
+        Ok(TsType::Function(params))
+    }
+
+    // Parses the parameters of a function type or call signature and returns their types
+    fn parse_params(&mut self) -> Result<Vec<TsType>, JsError> {
+        self.expect("(")?;
+        let mut resp: Vec<TsType> = Vec::new();
+        while !self.is(")") {
+            self.eat("...")?;
+            if self.eat("{")? {
+                self.skip_brackets("}")?;
+            } else if self.eat("[")? {
+                self.skip_brackets("]")?;
+            } else {
+                self.expect_name()?;
+            }
+            self.eat("?")?;
+            resp.push(if self.eat(":")? {
+                self.parse_type()?
+            } else {
+                TsType::Keyword(String::from("any"))
+            });
+            if !self.eat(",")? {
+                break;
+            }
+        }
+        self.expect(")")?;
+        Ok(resp)
+    }
+
+    // The members of a type literal or interface: { foo: string; bar?(): void }
+    fn parse_members(&mut self) -> Result<Vec<TsMember>, JsError> {
+        self.expect("{")?;
+        let mut resp: Vec<TsMember> = Vec::new();
+
+        while !self.eat("}")? {
+            if self.is("(") || self.is("<") {
+                // Call signature, (e: 'change', value: number): void
+                self.skip_type_parameters()?;
+                let params = self.parse_params()?;
+                if self.eat(":")? {
+                    self.parse_type()?;
+                }
+                resp.push(TsMember::CallSignature(params));
+            } else if self.is("new") && self.next_is("(")? {
+                // Construct signature
+                self.next()?;
+                self.parse_params()?;
+                if self.eat(":")? {
+                    self.parse_type()?;
+                }
+            } else if self.eat("[")? {
+                // Index signature ([key: string]: T) or computed key ([Symbol.iterator]: T)
+                self.skip_brackets("]")?;
+                self.eat("?")?;
+                if self.eat(":")? {
+                    self.parse_type()?;
+                } else if self.is("(") {
+                    self.parse_params()?;
+                    if self.eat(":")? {
+                        self.parse_type()?;
+                    }
+                }
+            } else {
+                if self.is("readonly") || self.is("get") || self.is("set") {
+                    let next = self.peek()?;
+                    if !self.token_is(&next, ":")
+                        && !self.token_is(&next, "?")
+                        && !self.token_is(&next, "(")
+                        && !self.token_is(&next, ";")
+                        && !self.token_is(&next, ",")
+                        && !self.token_is(&next, "}")
+                    {
+                        self.next()?;
+                    }
+                }
+
+                let key = match self.token.kind {
+                    TokenKind::Name | TokenKind::Number => self.text(&self.token),
+                    TokenKind::String => {
+                        let text = self.text(&self.token);
+                        text[1..text.len() - 1].to_string()
+                    }
+                    _ => return Err(self.unexpected()),
+                };
+                self.next()?;
+                let optional = self.eat("?")?;
+
+                let type_ = if self.is("(") || self.is("<") {
+                    // Method, foo(a: string): void
+                    self.skip_type_parameters()?;
+                    let params = self.parse_params()?;
+                    if self.eat(":")? {
+                        self.parse_type()?;
+                    }
+                    TsType::Function(params)
+                } else if self.eat(":")? {
+                    self.parse_type()?
+                } else {
+                    TsType::Keyword(String::from("any"))
+                };
+
+                resp.push(TsMember::Property {
+                    key,
+                    optional,
+                    type_,
+                });
+            }
+
+            if !self.eat(";")? && !self.eat(",")? && !self.is("}") && !self.token.newline_before {
+                return Err(self.unexpected());
+            }
+        }
+
+        Ok(resp)
+    }
+}
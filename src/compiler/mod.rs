@@ -16,21 +16,91 @@ pub struct Parser {
     pub current_char: usize,
     pub template: Option<Template>,
     pub script: Option<Script>,
+    pub script_setup: Option<ScriptSetup>,
     pub styles: Vec<Style>,
+    pub custom_blocks: Vec<CustomBlock>,
+    // The locations of the unique expressions of the v-bind() calls within the styles
+    pub css_vars: Vec<SourceLocation>,
 
     pub local_variables: HashMap<String, u16>,
+    pub options: CompilerOptions,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompilerOptions {
+    // The open and close delimiters of template interpolations
+    // Vue's default is ("{{", "}}"), alternatives like ("${", "}") or ("[[", "]]") are also possible
+    pub delimiters: (String, String),
+
+    // Keep html comments within the template and render them as comment nodes
+    // Same as the comments option of vue-template-compiler
+    pub comments: bool,
+
+    // Global variables that can be used within template expressions next to vue's allowed globals (Math, Date, JSON, ..)
+    // These are never prefixed with `_vm.`
+    pub allowed_globals: Vec<String>,
+
+    // Rewrite optional chaining (foo?.bar) and nullish coalescing (foo ?? bar) within template expressions
+    // into ES5 compatible javascript, the render function is not transpiled by vite
+    pub lower_optional_chaining: bool,
+
+    // Place the script within the main module of the component by rewriting its export default,
+    // instead of importing it from a separate module. Scripts with a lang that vite needs to transform
+    // (lang="ts") are still imported from a separate module
+    pub inline_script: bool,
+
+    // Name components after their file (user-card.vue -> UserCard) if the script doesn't define a name,
+    // the name is shown in the devtools and warnings and allows components to reference themselves
+    pub infer_component_name: bool,
+
+    // The project root, the scope id of a component is the hash of its path relative to the root
+    // so the scope id doesn't depend on where the project is located
+    pub root: Option<String>,
+
+    // Also hash the content of a component for its scope id
+    pub production: bool,
+}
+
+impl Default for CompilerOptions {
+    fn default() -> Self {
+        Self {
+            delimiters: (String::from("{{"), String::from("}}")),
+            comments: false,
+            allowed_globals: Vec::new(),
+            lower_optional_chaining: false,
+            inline_script: false,
+            infer_component_name: false,
+            root: None,
+            production: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Script {
     pub lang: Option<String>,
     pub content: SourceLocation,
+    // The file imported using <script src="..">, the content is empty if set
+    pub src: Option<String>,
+}
+
+// <script setup>, compiled into the setup function of the component
+#[derive(Debug, Clone)]
+pub struct ScriptSetup {
+    pub lang: Option<String>,
+    pub content: SourceLocation,
+    pub info: js::ScriptSetupInfo,
 }
 
 #[derive(Debug, Clone)]
 pub struct Template {
     pub lang: Option<String>,
+    pub functional: bool,
     pub content: Vec<template::Child>,
+    // The source of the template, from the end of the opening tag up to and including the closing tag
+    pub source: SourceLocation,
+    // The file imported using <template src="..">, the template is compiled once the file is read
+    pub src: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,15 +109,35 @@ pub enum Style {
     DirectScopedCSS(String),
 }
 
+// A top level block other than template, script and style, for example <i18n> or <docs>
+// These are not compiled, other vite plugins can transform them
+#[derive(Debug, Clone)]
+pub struct CustomBlock {
+    // The tag name of the block
+    pub type_: String,
+    pub lang: Option<String>,
+    // The static attributes of the block, attributes without a value have None as value
+    pub attrs: Vec<(String, Option<String>)>,
+    pub content: SourceLocation,
+}
+
 #[derive(Debug, Clone)]
 pub struct NormalStyle {
     pub lang: Option<String>,
     pub scoped: bool,
     pub content: SourceLocation,
+    // The file imported using <style src="..">, the content is empty if set
+    pub src: Option<String>,
+    // The key the css module is injected as, <style module> is injected as $style and <style module="foo"> as foo
+    pub module: Option<String>,
 }
 
 impl Parser {
     pub fn new(source: &str) -> Self {
+        Self::new_with_options(source, CompilerOptions::default())
+    }
+
+    pub fn new_with_options(source: &str, options: CompilerOptions) -> Self {
         let source_chars: Vec<char> = source.chars().collect();
         let source_chars_len = source_chars.len();
         return Self {
@@ -57,12 +147,25 @@ impl Parser {
             current_char: 0,
             template: None,
             script: None,
+            script_setup: None,
             styles: Vec::new(),
+            custom_blocks: Vec::new(),
+            css_vars: Vec::new(),
+            options,
         };
     }
 
+    #[cfg(test)]
     pub fn new_and_parse(source: &str, id: &str) -> Result<Self, ParserError> {
-        let mut p = Self::new(source);
+        Self::new_and_parse_with_options(source, id, CompilerOptions::default())
+    }
+
+    pub fn new_and_parse_with_options(
+        source: &str,
+        id: &str,
+        options: CompilerOptions,
+    ) -> Result<Self, ParserError> {
+        let mut p = Self::new_with_options(source, options);
         p.parse(id)?;
         Ok(p)
     }
@@ -86,6 +189,21 @@ impl Parser {
         }
     }
 
+    // Adds text that is not part of the input to the source so it can be referenced by a SourceLocation,
+    // for example the implicit div of a pug tag (.foo is equal to div.foo)
+    // The text is placed after the end of the input so the parser itself never reads it
+    pub fn add_virtual_source(&mut self, value: &str) -> SourceLocation {
+        let start = self.source_chars.len();
+        self.source_chars.extend(value.chars());
+        SourceLocation(start, self.source_chars.len())
+    }
+
+    // Checks if the next characters are equal to value without moving the cursor
+    fn next_is(&self, value: &[char]) -> bool {
+        let end = self.current_char + value.len();
+        end <= self.source_chars_len && &self.source_chars[self.current_char..end] == value
+    }
+
     fn read_one(&mut self) -> Option<char> {
         let resp = self.seek_one()?;
         self.current_char += 1;
@@ -118,6 +236,12 @@ impl Parser {
     }
 
     pub fn parse(&mut self, id: &str) -> Result<(), ParserError> {
+        // The start of the template content, the lang and if the template is functional
+        let mut template_start: Option<(usize, Option<String>, bool)> = None;
+        // The template is compiled again if <script setup> comes after it,
+        // the template needs to know the setup bindings
+        let mut recompile_template = false;
+
         while let Some(b) = self.read_one_skip_spacing() {
             match b {
                 '<' => {
@@ -128,7 +252,11 @@ impl Parser {
                         template::TagType::OpenAndClose(_) => return Err(ParserError::new(self, "tag type not allowed on top level")),
                     };
 
-                    let lang: Option<&str> = top_level_tag.1.args.has_attr_or_prop_with_string("lang");
+                    // Blocks with a src attribute import their content from another file, <style src="./style.scss">
+                    // The lang of these blocks is inferred from the file extension if not set
+                    let src: Option<String> = top_level_tag.1.args.has_attr_or_prop_with_string("src").map(|src| src.to_string());
+                    let src_lang: Option<String> = src.as_deref().and_then(lang_from_src);
+                    let lang: Option<&str> = top_level_tag.1.args.has_attr_or_prop_with_string("lang").or(src_lang.as_deref());
 
                     match top_level_tag.0 {
                         TopLevelTag::DocType | TopLevelTag::Comment => continue,
@@ -136,56 +264,238 @@ impl Parser {
                             if self.template.is_some() {
                                 return Err(ParserError::new(self, "can't have multiple templates in your code"));
                             }
-                            let children = template::compile(self)?;
-
-                            self.template = Some(Template{
-                                lang: if let Some(lang) = lang { Some(lang.to_string())} else {None},
-                                content: children,
-                            });
+                            let functional = top_level_tag.1.args.has_attr_or_prop("functional").is_some();
+                            let lang = lang.map(|lang| lang.to_string());
+                            if src.is_some() {
+                                // The content is compiled once it's read, see compile_template_src
+                                let start = self.current_char;
+                                self.look_for("</template>".chars().collect())?;
+                                self.template = Some(Template{
+                                    lang,
+                                    functional,
+                                    content: Vec::new(),
+                                    source: SourceLocation(start, start),
+                                    src,
+                                });
+                            } else {
+                                template_start = Some((self.current_char, lang.clone(), functional));
+                                self.parse_template(lang, functional)?;
+                            }
                         },
                         TopLevelTag::Script => {
-                            if self.script.is_some() {
+                            let setup = top_level_tag.1.args.has_attr_or_prop("setup").is_some();
+                            if setup && self.script_setup.is_some() {
+                                return Err(ParserError::new(self, "can't have multiple <script setup> in your code"));
+                            }
+                            if !setup && self.script.is_some() {
                                 return Err(ParserError::new(self, "can't have multiple scripts in your code"));
                             }
+                            if setup && src.is_some() {
+                                return Err(ParserError::new(self, "<script setup> cannot use the src attribute"));
+                            }
                             let script_start = self.current_char;
 
-                            js::compile_script_content(self)?;
-                            let content = SourceLocation(script_start, self.current_char - "</script>".len());
-
-                            self.script = Some(Script{
-                                lang: if let Some(lang) = lang { Some(lang.to_string())} else {None},
-                                content,
-                            });
+                            let content = if src.is_some() {
+                                self.look_for("</script>".chars().collect())?;
+                                SourceLocation(script_start, script_start)
+                            } else {
+                                js::compile_script_content(self)?;
+                                SourceLocation(script_start, self.current_char - "</script>".len())
+                            };
+                            let lang = if let Some(lang) = lang { Some(lang.to_string())} else {None};
+
+                            if setup {
+                                let info = js::analyze_script_setup(self, &content)?;
+                                self.script_setup = Some(ScriptSetup{
+                                    lang,
+                                    content,
+                                    info,
+                                });
+                                recompile_template = self.template.is_some();
+                            } else {
+                                self.script = Some(Script{
+                                    lang,
+                                    content,
+                                    src,
+                                });
+                            }
                         },
                         TopLevelTag::Style => {
                             let scoped = top_level_tag.1.args.has_attr_or_prop("scoped").is_some();
+                            let module = match top_level_tag.1.args.has_attr_or_prop("module") {
+                                Some(template::StaticOrJS::Static(name)) if !name.is_empty() => Some(name.clone()),
+                                Some(template::StaticOrJS::Bind(_)) => {
+                                    return Err(ParserError::new_at(self, &top_level_tag.1.name, "the module name of a style can't be bound, use module=\"name\" instead"));
+                                }
+                                Some(_) => Some(String::from("$style")),
+                                None => None,
+                            };
+                            if let Some(name) = module.as_ref() {
+                                let not_unique = self.styles.iter().any(|style| match style {
+                                    Style::Normal(style) => style.module.as_ref() == Some(name),
+                                    Style::DirectScopedCSS(_) => false,
+                                });
+                                if not_unique {
+                                    return Err(ParserError::new(self, format!("css module name \"{}\" is not unique", name)));
+                                }
+                            }
 
                             match (scoped, lang) {
-                                (true, None) | (true, Some("css")) => {
+                                // Css modules are imported so their class map can be injected into the component
+                                (true, None) | (true, Some("css")) if src.is_none() && module.is_none() => {
                                     let start = self.current_char;
                                     let injection_points = style::parse_scoped_css(self, style::SelectorsEnd::StyleClosure)?;
                                     let style_location = SourceLocation(start, self.current_char-8);
+                                    self.add_css_vars(&style_location);
                                     let scoped_style = style::gen_scoped_css(self, style_location, injection_points, id);
                                     self.styles.push(Style::DirectScopedCSS(scoped_style))
                                 }
                                 _ => {
                                     let style_start = self.current_char;
                                     let style_end = self.look_for("</style>".chars().collect())?.0;
-                                    let content = SourceLocation(style_start, style_end);
+                                    let content = if src.is_some() {
+                                        SourceLocation(style_start, style_start)
+                                    } else {
+                                        SourceLocation(style_start, style_end)
+                                    };
+                                    self.add_css_vars(&content);
 
                                     self.styles.push(Style::Normal(NormalStyle{
                                         lang: if let Some(lang) = lang { Some(lang.to_string())} else {None},
                                         scoped,
                                         content,
+                                        src,
+                                        module,
                                     }));
                                 }
                             };
                         },
+                        TopLevelTag::Custom => {
+                            let type_ = top_level_tag.1.name.string(self);
+                            let mut attrs: Vec<(String, Option<String>)> = Vec::new();
+                            for (key, value) in top_level_tag.1.args.attrs_or_props.iter().flatten() {
+                                match value {
+                                    template::StaticOrJS::Non => attrs.push((key.clone(), None)),
+                                    template::StaticOrJS::Static(value) => attrs.push((key.clone(), Some(value.clone()))),
+                                    template::StaticOrJS::Bind(_) => {},
+                                }
+                            }
+
+                            let block_start = self.current_char;
+                            let block_end = self.look_for(format!("</{}>", type_).chars().collect())?.0;
+
+                            self.custom_blocks.push(CustomBlock{
+                                type_,
+                                lang: lang.map(|lang| lang.to_string()),
+                                attrs,
+                                content: SourceLocation(block_start, block_end),
+                            });
+                        },
                     }
                 },
                 c => return Err(ParserError::new(self, format!("found invalid character in source: '{}', expected <template ..> <script ..> or <style ..>", c))),
             };
         }
+
+        if let (Some(script), Some(script_setup)) =
+            (self.script.as_ref(), self.script_setup.as_ref())
+        {
+            if script.lang != script_setup.lang {
+                return Err(ParserError::new_at(
+                    self,
+                    &script_setup.content,
+                    "<script> and <script setup> must have the same lang",
+                ));
+            }
+        }
+
+        if let (true, Some((start, lang, functional))) = (recompile_template, template_start) {
+            let end = self.current_char;
+            self.current_char = start;
+            self.parse_template(lang, functional)?;
+            self.current_char = end;
+        }
+
+        if let Some(template) = self.template.as_ref() {
+            if let (true, Some(css_var)) = (template.functional, self.css_vars.first()) {
+                return Err(ParserError::new_at(
+                    self,
+                    css_var,
+                    "v-bind() within styles can't be used in combination with a functional template",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Adds the expressions of the v-bind() calls within a style to the css vars
+    // The css vars point to the expressions within the style so errors are reported at the right location
+    fn add_css_vars(&mut self, style: &SourceLocation) {
+        let css = style.string(self);
+        for v_bind in style::find_v_binds(&css) {
+            let exists = self
+                .css_vars
+                .iter()
+                .any(|css_var| css_var.string(self) == v_bind.expression);
+            if !exists {
+                let start = style.0 + css[..v_bind.expression_range.start].chars().count();
+                let end = start + v_bind.expression.chars().count();
+                self.css_vars.push(SourceLocation(start, end));
+            }
+        }
+    }
+
+    // Compiles the content of a <template src=".."> block, the content of the referenced file is added as virtual source
+    // Does nothing if the template doesn't have a src attribute
+    pub fn compile_template_src(&mut self, content: &str) -> Result<(), ParserError> {
+        let (lang, functional, src) = match self.template.as_ref() {
+            Some(Template {
+                lang,
+                functional,
+                src: Some(src),
+                ..
+            }) => (lang.clone(), *functional, src.clone()),
+            _ => return Ok(()),
+        };
+
+        let location = self.add_virtual_source(&format!("{}</template>", content));
+        let (current_char, source_chars_len) = (self.current_char, self.source_chars_len);
+        self.current_char = location.0;
+        self.source_chars_len = location.1;
+
+        let result = self.parse_template(lang, functional);
+
+        self.current_char = current_char;
+        self.source_chars_len = source_chars_len;
+        result?;
+
+        if let Some(template) = self.template.as_mut() {
+            template.src = Some(src);
+        }
+        Ok(())
+    }
+
+    // Compiles the template content, expects the cursor to be right after the opening template tag
+    fn parse_template(
+        &mut self,
+        lang: Option<String>,
+        functional: bool,
+    ) -> Result<(), ParserError> {
+        let start = self.current_char;
+
+        let children = match lang.as_deref() {
+            Some("pug") => template::pug::compile(self)?,
+            _ => template::compile(self)?,
+        };
+
+        self.template = Some(Template {
+            lang,
+            functional,
+            content: children,
+            source: SourceLocation(start, self.current_char),
+            src: None,
+        });
         Ok(())
     }
 
@@ -225,23 +535,13 @@ impl Parser {
         } else if parsed_tag.name.eq(self, &mut "style".chars()) {
             TopLevelTag::Style
         } else {
-            return Err(ParserError::new(
-                self,
-                format!(
-                    "tag <{}> is not allowed on the top level ",
-                    parsed_tag.name.string(self)
-                ),
-            ));
+            TopLevelTag::Custom
         };
 
         Ok((top_level_tag, parsed_tag))
     }
 
-    fn parse_quotes(
-        &mut self,
-        kind: QuoteKind,
-        global_references: &mut Option<Vec<SourceLocation>>,
-    ) -> Result<(), ParserError> {
+    fn parse_quotes(&mut self, kind: QuoteKind) -> Result<(), ParserError> {
         let quote_char = match kind {
             QuoteKind::JSDouble => '"',
             QuoteKind::JSSingle => '\'',
@@ -262,7 +562,7 @@ impl Parser {
                 }
                 '$' if is_js_backtick && self.must_seek_one()? == '{' => {
                     self.current_char += 1;
-                    js::parse_block_like(self, '}', global_references)?;
+                    js::parse_block_like(self, '}')?;
                 }
                 c if c == quote_char => return Ok(()),
                 _ => {}
@@ -271,6 +571,18 @@ impl Parser {
     }
 }
 
+// Returns the lang of a file based on its extension, ./foo.scss -> scss
+fn lang_from_src(src: &str) -> Option<String> {
+    let path = src.split(['?', '#']).next().unwrap_or(src);
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    match file_name.rsplit_once('.') {
+        Some((name, extension)) if !name.is_empty() && !extension.is_empty() => {
+            Some(extension.to_string())
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 enum QuoteKind {
     JSDouble,   // "
@@ -418,4 +730,5 @@ enum TopLevelTag {
     Template,
     Script,
     Style,
+    Custom,
 }
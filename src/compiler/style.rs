@@ -1,4 +1,5 @@
 use super::{utils, Parser, ParserError, QuoteKind, SourceLocation};
+use std::ops::Range;
 
 /*
     TODO: Support :NOT(.foo,.bar)
@@ -188,8 +189,8 @@ fn parse_at(
 
         loop {
             match p.must_read_one()? {
-                '\'' => p.parse_quotes(QuoteKind::JSSingle, &mut None)?,
-                '"' => p.parse_quotes(QuoteKind::JSDouble, &mut None)?,
+                '\'' => p.parse_quotes(QuoteKind::JSSingle)?,
+                '"' => p.parse_quotes(QuoteKind::JSDouble)?,
                 '{' => break,
                 '(' => parse_arg(p, injection_points)?,
                 ';' => {
@@ -235,8 +236,8 @@ fn parse_arg(
 ) -> Result<(), ParserError> {
     loop {
         match p.must_read_one()? {
-            '\'' => p.parse_quotes(QuoteKind::JSSingle, &mut None)?,
-            '"' => p.parse_quotes(QuoteKind::JSDouble, &mut None)?,
+            '\'' => p.parse_quotes(QuoteKind::JSSingle)?,
+            '"' => p.parse_quotes(QuoteKind::JSDouble)?,
             ')' => return Ok(()),
             '/' if p.seek_one_or_null() == '*' => {
                 // This is the start of a comment
@@ -253,8 +254,8 @@ fn parse_selector_content(
 ) -> Result<(), ParserError> {
     loop {
         match p.must_read_one()? {
-            '\'' => p.parse_quotes(QuoteKind::JSSingle, &mut None)?,
-            '"' => p.parse_quotes(QuoteKind::JSDouble, &mut None)?,
+            '\'' => p.parse_quotes(QuoteKind::JSSingle)?,
+            '"' => p.parse_quotes(QuoteKind::JSDouble)?,
             '}' => return Ok(()),
             '{' => parse_selector_content(p, injection_points)?,
             '/' if p.seek_one_or_null() == '*' => {
@@ -525,3 +526,184 @@ fn parse_comment(p: &mut Parser) -> Result<InjectionPoint, ParserError> {
         }
     }
 }
+
+// parses the declarations of a inline style attribute: color: red; font-size: 12px
+// Semicolons and colons inside quotes or parentheses (url(data:image/png;base64,..)) are part of the value
+// The !important flag is kept as part of the value, vue handles it when applying the style
+// Html entities are decoded first so the ; of &quot; doesn't split a declaration
+pub fn parse_inline_style(style: &str) -> Vec<(String, String)> {
+    let style = utils::decode_attr_entities(style);
+    let style = style.as_str();
+    let mut declarations: Vec<(String, String)> = Vec::new();
+    let mut add_declaration = |declaration: &str| {
+        if let Some((key, value)) = declaration.split_once(':') {
+            let (key, value) = (key.trim(), value.trim());
+            if !key.is_empty() && !value.is_empty() {
+                declarations.push((key.to_string(), value.to_string()));
+            }
+        }
+    };
+
+    let mut quote: Option<char> = None;
+    let mut parentheses_depth = 0usize;
+    let mut declaration_start = 0;
+    for (idx, c) in style.char_indices() {
+        match (c, quote) {
+            (c, Some(quote_char)) if c == quote_char => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(', None) => parentheses_depth += 1,
+            (')', None) => parentheses_depth = parentheses_depth.saturating_sub(1),
+            (';', None) if parentheses_depth == 0 => {
+                add_declaration(&style[declaration_start..idx]);
+                declaration_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    add_declaration(&style[declaration_start..]);
+
+    declarations
+}
+
+// A v-bind() within the declarations of a style: color: v-bind(themeColor)
+pub struct CssVBind {
+    // The byte range of the full v-bind(..) call within the style
+    pub range: Range<usize>,
+    // The javascript expression, quotes around the expression are removed: v-bind('theme.color') -> theme.color
+    pub expression: String,
+    // The byte range of the expression within the style
+    pub expression_range: Range<usize>,
+}
+
+// Finds the v-bind() calls within a style, comments and strings are skipped
+pub fn find_v_binds(css: &str) -> Vec<CssVBind> {
+    let bytes = css.as_bytes();
+    let mut v_binds: Vec<CssVBind> = Vec::new();
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'/' if bytes.get(idx + 1) == Some(&b'*') => {
+                idx = match css[idx + 2..].find("*/") {
+                    Some(end) => idx + 2 + end + 2,
+                    None => bytes.len(),
+                };
+            }
+            quote @ (b'\'' | b'"') => {
+                idx = match css[idx + 1..].find(quote as char) {
+                    Some(end) => idx + 1 + end + 1,
+                    None => bytes.len(),
+                };
+            }
+            b'v' if css[idx..].starts_with("v-bind(")
+                && (idx == 0 || !is_css_name_part(bytes[idx - 1])) =>
+            {
+                match parse_v_bind_args(css, idx + "v-bind(".len()) {
+                    Some((expression_range, end)) => {
+                        v_binds.push(CssVBind {
+                            range: idx..end,
+                            expression: css[expression_range.clone()].to_string(),
+                            expression_range,
+                        });
+                        idx = end;
+                    }
+                    None => idx += 1,
+                }
+            }
+            _ => idx += 1,
+        }
+    }
+
+    v_binds
+}
+
+// Parses the argument of v-bind(..), expects start to be right after the opening parenthesis
+// Returns the byte range of the expression and the byte index after the closing parenthesis
+fn parse_v_bind_args(css: &str, start: usize) -> Option<(Range<usize>, usize)> {
+    let rest = &css[start..];
+    let trimmed = rest.trim_start();
+    let offset = start + rest.len() - trimmed.len();
+
+    let (expression_start, expression, after_expression) = match trimmed.chars().next()? {
+        quote @ ('\'' | '"') => {
+            let end = trimmed[1..].find(quote)?;
+            (offset + 1, &trimmed[1..end + 1], offset + end + 2)
+        }
+        _ => {
+            let mut depth = 0usize;
+            let end = trimmed.char_indices().find_map(|(idx, c)| match c {
+                '(' => {
+                    depth += 1;
+                    None
+                }
+                ')' if depth == 0 => Some(idx),
+                ')' => {
+                    depth -= 1;
+                    None
+                }
+                _ => None,
+            })?;
+            (offset, &trimmed[..end], offset + end)
+        }
+    };
+
+    let after = &css[after_expression..];
+    let closing = after.len() - after.trim_start().len();
+    if !after[closing..].starts_with(')') {
+        return None;
+    }
+
+    let expression_start = expression_start + expression.len() - expression.trim_start().len();
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return None;
+    }
+    Some((
+        expression_start..expression_start + expression.len(),
+        after_expression + closing + 1,
+    ))
+}
+
+fn is_css_name_part(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+// Returns the name of the css custom property a v-bind() expression is bound to: --0a1b2c3d4e5f6a7b-color
+// Expressions that are not a plain name get the hash of the expression as suffix so theme.bg and theme_bg
+// never share a property: --0a1b2c3d4e5f6a7b-theme_bg-5f3e1a2b6c7d8e9f
+// Plain names never contain a -, so they can't conflict with the hashed names
+pub fn css_var_name(id: &str, expression: &str) -> String {
+    let mut resp = format!("--{}-", id);
+    let is_plain_name = expression
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    for c in expression.chars() {
+        resp.push(if c.is_ascii_alphanumeric() || c == '_' {
+            c
+        } else {
+            '_'
+        });
+    }
+    if !is_plain_name {
+        resp.push('-');
+        resp.push_str(&utils::hash(expression));
+    }
+    resp
+}
+
+// Replaces the v-bind() calls within a style with the css custom properties they are bound to
+// color: v-bind(themeColor) -> color: var(--0a1b2c3d4e5f6a7b-themeColor)
+pub fn rewrite_v_binds(css: &str, id: &str) -> String {
+    let mut resp = String::with_capacity(css.len());
+    let mut last = 0;
+    for v_bind in find_v_binds(css) {
+        resp.push_str(&css[last..v_bind.range.start]);
+        resp.push_str("var(");
+        resp.push_str(&css_var_name(id, &v_bind.expression));
+        resp.push(')');
+        last = v_bind.range.end;
+    }
+    resp.push_str(&css[last..]);
+    resp
+}
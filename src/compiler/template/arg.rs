@@ -1,5 +1,6 @@
+use super::super::js::{self, TemplateJs};
 use super::super::utils::is_space;
-use super::super::{js, Parser, ParserError, SourceLocation};
+use super::super::{Parser, ParserError, QuoteKind, SourceLocation};
 use super::{add_or_set, StaticOrJS, TagKind, VueTagArgs};
 
 pub fn try_parse(
@@ -8,25 +9,26 @@ pub fn try_parse(
     result: &mut VueTagArgs,
     v_else_allowed: bool,
     tag_kind: &TagKind,
+    syntax: ArgsSyntax,
 ) -> Result<Option<char>, ParserError> {
-    if !is_start_of_arg(c) {
+    if !is_arg_name_char(c, syntax) {
         return Ok(None);
     }
 
-    let (name_result, next_c) = parse_arg_name(p, c)?;
+    let (name_result, next_c) = parse_arg_name(p, c, syntax)?;
     c = next_c;
 
     let (expect_value, target_allowed, modifier_allowed, arg_kind) = match name_result.name.as_str()
     {
         "v-if" => (ExpectValue::Yes, false, false, VueArgKind::If),
-        "v-pre" => (ExpectValue::Yes, false, false, VueArgKind::Pre),
+        "v-pre" => (ExpectValue::No, false, false, VueArgKind::Pre),
         "v-else" => (ExpectValue::No, false, false, VueArgKind::Else),
         "v-slot" => (ExpectValue::Both, true, false, VueArgKind::Slot),
         "v-text" => (ExpectValue::Yes, false, false, VueArgKind::Text),
         "v-html" => (ExpectValue::Yes, false, false, VueArgKind::Html),
         "v-once" => (ExpectValue::No, false, false, VueArgKind::Once),
         "v-model" => (ExpectValue::Yes, true, true, VueArgKind::Model),
-        "v-cloak" => (ExpectValue::Yes, false, false, VueArgKind::Cloak),
+        "v-cloak" => (ExpectValue::No, false, false, VueArgKind::Cloak),
         "v-else-if" => (ExpectValue::Yes, false, false, VueArgKind::ElseIf),
         "v-for" => (ExpectValue::Yes, false, false, VueArgKind::For),
         "v-bind" => (ExpectValue::Yes, true, true, VueArgKind::Bind),
@@ -60,6 +62,19 @@ pub fn try_parse(
         ));
     }
 
+    // Dynamic arguments (:[key], @[event]) can't be written as a static key in the render function
+    if let Some(target) = name_result.target.as_ref() {
+        if target.starts_with('[') {
+            return Err(ParserError::new(
+                p,
+                format!(
+                    "dynamic argument {} on {} is not supported",
+                    target, name_result.name
+                ),
+            ));
+        }
+    }
+
     match expect_value {
         ExpectValue::Yes if !name_result.parse_value_next => {
             return Err(ParserError::new(
@@ -84,7 +99,7 @@ pub fn try_parse(
 
     match arg_kind {
         VueArgKind::Default => {
-            let (contents, next_c) = might_get_arg_value(p, &name_result, c)?;
+            let (contents, next_c) = might_get_arg_value(p, &name_result, c, syntax)?;
             c = next_c;
             match tag_kind {
                 TagKind::Slot if name_result.name == "name" => {
@@ -97,7 +112,7 @@ pub fn try_parse(
             }
         }
         VueArgKind::Bind => {
-            let (js_content, next_c) = get_arg_js_value(p)?;
+            let (js_content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
 
             let contents = StaticOrJS::Bind(js_content);
@@ -118,8 +133,9 @@ pub fn try_parse(
             }
         }
         VueArgKind::On => {
-            let (content, next_c) = get_arg_js_value(p)?;
-            c = next_c;
+            let js_location = get_arg_js_location(p)?;
+            let content = js::compile_template_handler(p, &js_location)?;
+            c = p.must_read_one()?;
             let target = if let Some(target) = name_result.target {
                 target
             } else {
@@ -129,7 +145,7 @@ pub fn try_parse(
             result.has_js_component_args = true;
         }
         VueArgKind::Text => {
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
             add_or_set(
                 &mut result.dom_props,
@@ -138,13 +154,13 @@ pub fn try_parse(
             result.has_js_component_args = true;
         }
         VueArgKind::Html => {
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
             add_or_set(&mut result.dom_props, (String::from("innerHTML"), content));
             result.has_js_component_args = true;
         }
         VueArgKind::If => {
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
             result.set_modifier(p, VueTagModifier::If(content))?;
         }
@@ -164,7 +180,7 @@ pub fn try_parse(
                     "v-else-if can only be used after en v-if element",
                 ));
             }
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
             result.set_modifier(p, VueTagModifier::ElseIf(content))?;
         }
@@ -185,7 +201,7 @@ pub fn try_parse(
             result.set_modifier(p, VueTagModifier::For(content))?;
         }
         VueArgKind::Model => {
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::AssignmentTarget)?;
             c = next_c;
 
             add_or_set(
@@ -193,7 +209,7 @@ pub fn try_parse(
                 (
                     String::from("input"),
                     format!(
-                        "$event.target.composing?undefined:{}=$event.target.value",
+                        "function($event){{$event.target.composing?undefined:{}=$event.target.value}}",
                         &content
                     ),
                 ),
@@ -234,17 +250,14 @@ pub fn try_parse(
             c = next_c;
             result.slot = Some((name_result.target.unwrap(), content));
         }
-        VueArgKind::Pre => {
-            todo!("support pre");
-        }
-        VueArgKind::Cloak => {
-            todo!("support cloak");
-        }
-        VueArgKind::Once => {
-            todo!("support once");
+        VueArgKind::Pre | VueArgKind::Cloak | VueArgKind::Once => {
+            return Err(ParserError::new(
+                p,
+                format!("{} is not supported", name_result.name),
+            ));
         }
         VueArgKind::CustomDirective(_) => {
-            let (content, next_c) = get_arg_js_value(p)?;
+            let (content, next_c) = get_arg_js_value(p, TemplateJs::Expression)?;
             c = next_c;
 
             add_or_set(&mut result.directives, (name_result, content));
@@ -261,14 +274,22 @@ fn might_get_js_value(
     c: char,
 ) -> Result<(Option<String>, char), ParserError> {
     Ok(if name.parse_value_next {
-        let (contents, c) = get_arg_js_value(p)?;
+        let (contents, c) = get_arg_js_value(p, TemplateJs::Params)?;
         (Some(contents), c)
     } else {
         (None, c)
     })
 }
 
-fn get_arg_js_value(p: &mut Parser) -> Result<(String, char), ParserError> {
+fn get_arg_js_value(p: &mut Parser, kind: TemplateJs) -> Result<(String, char), ParserError> {
+    let js_location = get_arg_js_location(p)?;
+    let value = js::compile_template_js(p, &js_location, kind)?;
+    let c = p.must_read_one()?;
+    Ok((value, c))
+}
+
+// Parses a quoted argument value and returns the location of the javascript within the quotes
+fn get_arg_js_location(p: &mut Parser) -> Result<SourceLocation, ParserError> {
     let closure = p.must_read_one()?;
     match closure {
         '"' | '\'' => {} // Ok
@@ -282,38 +303,37 @@ fn get_arg_js_value(p: &mut Parser) -> Result<(String, char), ParserError> {
             ))
         }
     }
-    let start = p.current_char;
-    let replacements = js::parse_template_arg(p, closure)?;
-    let sl = SourceLocation(start, p.current_char - 1);
-    let c = p.must_read_one()?;
-
-    let value = js::add_vm_references(p, &sl, &replacements);
-    Ok((value, c))
+    js::parse_template_arg(p, closure)
 }
 
 fn might_get_arg_value(
     p: &mut Parser,
     name: &ParseArgNameResult,
     c: char,
+    syntax: ArgsSyntax,
 ) -> Result<(StaticOrJS, char), ParserError> {
     Ok(if name.parse_value_next {
-        let (contents, c) = get_arg_value(p)?;
+        let (contents, c) = get_arg_value(p, syntax)?;
         (contents, c)
     } else {
         (StaticOrJS::Non, c)
     })
 }
 
-fn get_arg_value(p: &mut Parser) -> Result<(StaticOrJS, char), ParserError> {
+fn get_arg_value(p: &mut Parser, syntax: ArgsSyntax) -> Result<(StaticOrJS, char), ParserError> {
     let mut c = p.must_read_one_skip_spacing()?;
     let quote = match c {
         '\'' => '\'',
         '"' => '"',
+        _ if syntax == ArgsSyntax::Pug => {
+            p.current_char -= 1;
+            return get_pug_js_value(p);
+        }
         _ => {
             let mut resp = c.to_string();
             loop {
                 c = p.must_read_one()?;
-                if is_space(c) || c == '/' || c == '>' {
+                if is_arg_end(c, syntax) {
                     return Ok((StaticOrJS::Static(resp), c));
                 }
                 resp.push(c);
@@ -333,10 +353,73 @@ fn get_arg_value(p: &mut Parser) -> Result<(StaticOrJS, char), ParserError> {
     Ok((StaticOrJS::Static(resp), p.must_read_one()?))
 }
 
-fn is_start_of_arg(c: char) -> bool {
+// Unquoted pug argument values are javascript expressions, a(href=url) is equal to a(:href="url")
+// The value ends at a comma or closing parenthesis outside of strings and brackets, or at a space
+// if the expression is complete (title=item.name + '!' continues after the spaces)
+fn get_pug_js_value(p: &mut Parser) -> Result<(StaticOrJS, char), ParserError> {
+    let start = p.current_char;
+    let mut depth = 0usize;
+    // The amount of ? of a ternary without the matching :
+    let mut open_ternaries = 0usize;
+    let mut last = ' ';
+    let c = loop {
+        let c = p.must_read_one()?;
+        match c {
+            '\'' => p.parse_quotes(QuoteKind::JSSingle)?,
+            '"' => p.parse_quotes(QuoteKind::JSDouble)?,
+            '`' => p.parse_quotes(QuoteKind::JSBacktick)?,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ')' | ',' if depth == 0 => break c,
+            '?' if depth == 0 && p.seek_one_or_null() != '.' && p.seek_one_or_null() != '?' => {
+                open_ternaries += 1
+            }
+            ':' if depth == 0 => open_ternaries = open_ternaries.saturating_sub(1),
+            c if depth == 0 && is_space(c) => {
+                while is_space(p.seek_one_or_null()) {
+                    p.current_char += 1;
+                }
+                let next = p.seek_one_or_null();
+                let continues = "+-*/%?|&<>=!.".contains(last)
+                    || "+*/%?|&<>=.".contains(next)
+                    || (next == ':' && open_ternaries > 0);
+                if !continues {
+                    break c;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        last = c;
+    };
+
+    let js_location = SourceLocation(start, p.current_char - 1);
+    let value = js::compile_template_js(p, &js_location, TemplateJs::Expression)?;
+    Ok((StaticOrJS::Bind(value), c))
+}
+
+// The syntax the tag arguments are written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgsSyntax {
+    Html, // <div foo="bar" :baz="qux">
+    Pug,  // div(foo="bar", :baz="qux")
+}
+
+// Returns true if c ends the name or unquoted value of an argument
+fn is_arg_end(c: char, syntax: ArgsSyntax) -> bool {
     match c {
-        '@' | ':' | 'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => true,
-        _ => false,
+        '/' | '>' => true,
+        ',' | ')' if syntax == ArgsSyntax::Pug => true,
+        c => is_space(c),
+    }
+}
+
+// Returns true if c can be part of an argument name
+// HTML allows almost every character in a attribute name (xlink:href, data-foo.bar, @click)
+fn is_arg_name_char(c: char, syntax: ArgsSyntax) -> bool {
+    match c {
+        '"' | '\'' | '=' | '<' => false,
+        c => !c.is_control() && !is_arg_end(c, syntax),
     }
 }
 
@@ -355,111 +438,44 @@ pub struct ParseArgNameResult {
     pub modifiers: Option<Vec<String>>, // `trim` of `v-bind:some_value.trim`
 }
 
-fn parse_arg_name(p: &mut Parser, mut c: char) -> Result<(ParseArgNameResult, char), ParserError> {
-    let invalid_character_err = |p: &mut Parser, c: char| {
-        Err(ParserError::new(
-            p,
-            format!("invalid argument character '{}'", c.to_string()),
-        ))
-    };
-
-    let mut name = String::new();
-
-    let mut parse_target_next = false;
-    let mut parse_modifier_next = false;
+fn parse_arg_name(
+    p: &mut Parser,
+    mut c: char,
+    syntax: ArgsSyntax,
+) -> Result<(ParseArgNameResult, char), ParserError> {
+    let mut raw_name = c.to_string();
     let mut parse_value_next = false;
 
-    match c {
-        '@' => {
-            name = String::from("v-on");
-            parse_target_next = true;
-        }
-        ':' => {
-            name = String::from("v-bind");
-            parse_target_next = true;
-        }
-        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
-            name.push(c);
-            loop {
-                c = p.must_read_one()?;
-                match c {
-                    'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => name.push(c),
-                    ':' => {
-                        parse_target_next = true;
-                        break;
-                    }
-                    '.' => {
-                        parse_modifier_next = true;
-                        break;
-                    }
-                    '=' => {
-                        parse_value_next = true;
-                        break;
-                    }
-                    '/' | '>' => break,
-                    c if is_space(c) => break,
-                    c => return invalid_character_err(p, c),
-                }
-            }
+    loop {
+        c = p.must_read_one()?;
+        if c == '=' {
+            parse_value_next = true;
+            break;
         }
-        c => return invalid_character_err(p, c),
-    };
-
-    let target: Option<String> = if parse_target_next {
-        let mut target = String::new();
-        loop {
-            c = p.must_read_one()?;
-            match c {
-                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => target.push(c),
-                '.' => {
-                    parse_modifier_next = true;
-                    break;
-                }
-                '=' => {
-                    parse_value_next = true;
-                    break;
-                }
-                '/' | '>' | ' ' => break,
-                c if is_space(c) => break,
-                c => return invalid_character_err(p, c),
-            }
+        if !is_arg_name_char(c, syntax) {
+            break;
         }
-        Some(target)
-    } else {
-        None
-    };
+        raw_name.push(c);
+    }
 
-    let modifiers: Option<Vec<String>> = if parse_modifier_next {
-        let mut modifiers: Vec<String> = Vec::new();
-        'outer: loop {
-            let mut modifier = String::new();
-            loop {
-                c = p.must_read_one()?;
-                match c {
-                    'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => modifier.push(c),
-                    '.' => {
-                        break;
-                    }
-                    '=' => {
-                        parse_value_next = true;
-                        modifiers.push(modifier);
-                        break 'outer;
-                    }
-                    '/' | '>' => break,
-                    c if is_space(c) => break,
-                    c => return invalid_character_err(p, c),
-                }
+    // Only directives are split up into a name, target and modifiers, other arguments keep their name as is
+    let (name, target, modifiers) = match raw_name.chars().next() {
+        Some('@') => split_directive_shorthand("v-on", &raw_name[1..]),
+        Some(':') => split_directive_shorthand("v-bind", &raw_name[1..]),
+        Some('#') => split_directive_shorthand("v-slot", &raw_name[1..]),
+        _ if raw_name.starts_with("v-") => {
+            let (name_and_target, modifiers) = split_modifiers(&raw_name);
+            match name_and_target.split_once(':') {
+                Some((name, target)) => (name.to_string(), Some(target.to_string()), modifiers),
+                None => (name_and_target.to_string(), None, modifiers),
             }
-            modifiers.push(modifier);
         }
-        Some(modifiers)
-    } else {
-        None
+        _ => (raw_name, None, None),
     };
 
     Ok((
         ParseArgNameResult {
-            parse_value_next: parse_value_next,
+            parse_value_next,
             name,
             target,
             modifiers,
@@ -468,6 +484,31 @@ fn parse_arg_name(p: &mut Parser, mut c: char) -> Result<(ParseArgNameResult, ch
     ))
 }
 
+// Splits a shorthand like `@click.prevent` (without the @) into the directive name, target and modifiers
+fn split_directive_shorthand(
+    name: &str,
+    value: &str,
+) -> (String, Option<String>, Option<Vec<String>>) {
+    let (target, modifiers) = split_modifiers(value);
+    (name.to_string(), Some(target.to_string()), modifiers)
+}
+
+// Splits `click.prevent.stop` into `click` and `[prevent, stop]`
+// Dots within a dynamic argument (`[foo.bar]`) are not seen as modifiers
+fn split_modifiers(value: &str) -> (&str, Option<Vec<String>>) {
+    let search_from = value.rfind(']').unwrap_or(0);
+    match value[search_from..].find('.') {
+        Some(idx) => {
+            let (name, modifiers) = value.split_at(search_from + idx);
+            (
+                name,
+                Some(modifiers[1..].split('.').map(String::from).collect()),
+            )
+        }
+        None => (value, None),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ParsedVFor {
     pub value: String,
@@ -600,10 +641,8 @@ fn parse_v_for_value(p: &mut Parser) -> Result<ParsedVFor, ParserError> {
         ));
     }
 
-    let start = p.current_char;
-    let replacements = js::parse_template_arg(p, closure)?;
-    let list_location = SourceLocation(start, p.current_char - 1);
-    result.list = js::add_vm_references(p, &list_location, &replacements);
+    let list_location = js::parse_template_arg(p, closure)?;
+    result.list = js::compile_template_js(p, &list_location, TemplateJs::Expression)?;
 
     Ok(result)
 }
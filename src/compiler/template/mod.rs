@@ -1,8 +1,10 @@
 mod arg;
+pub mod pug;
 pub mod to_js;
 
 use super::utils::is_space;
-use super::{js, Parser, ParserError, SourceLocation};
+use super::{js, style, Parser, ParserError, SourceLocation};
+use arg::ArgsSyntax;
 
 // parse_tag is expected to be next to the open indicator (<) at the first character of the tag name
 // TODO support upper case tag names
@@ -48,11 +50,13 @@ pub fn parse_tag(p: &mut Parser, v_else_allowed: bool) -> Result<Tag, ParserErro
             }
             '-' => {
                 if p.must_read_one()? == '-' {
-                    p.look_for(vec!['-', '-', '>'])?;
+                    let content_start = p.current_char;
+                    let closure = p.look_for(vec!['-', '-', '>'])?;
 
+                    // For comments the name is the comment content
                     return Ok(Tag {
                         type_: TagType::Comment,
-                        name: SourceLocation::empty(),
+                        name: SourceLocation(content_start, closure.0),
                         args: VueTagArgs::new(),
                     });
                 }
@@ -105,7 +109,7 @@ pub fn parse_tag(p: &mut Parser, v_else_allowed: bool) -> Result<Tag, ParserErro
     // Parse args
     loop {
         c = p.must_read_one_skip_spacing()?;
-        c = match arg::try_parse(p, c, &mut args, v_else_allowed, &kind)? {
+        c = match arg::try_parse(p, c, &mut args, v_else_allowed, &kind, ArgsSyntax::Html)? {
             Some(next_char) => next_char,
             None => c,
         };
@@ -192,11 +196,81 @@ pub fn compile(p: &mut Parser) -> Result<Vec<Child>, ParserError> {
     }
 }
 
+// Validates that the template has exactly one root element or a v-if / v-else-if / v-else chain of root elements
+// This follows the checks of vue-template-compiler
+pub fn validate_root(p: &Parser, roots: &[Child]) -> Result<(), ParserError> {
+    let mut previous_root_opened_if = false;
+
+    for (idx, root) in roots.iter().enumerate() {
+        let tag = match root {
+            Child::Tag(tag, _) => tag,
+            Child::Text(location) => {
+                return Err(ParserError::new_at(
+                    p,
+                    location,
+                    "text outside the root element is not allowed",
+                ))
+            }
+            Child::Var(_, location) => {
+                return Err(ParserError::new_at(
+                    p,
+                    location,
+                    "text outside the root element is not allowed",
+                ))
+            }
+            Child::Comment(_) => continue,
+        };
+
+        let is_if_chain_continuation = matches!(
+            tag.args.modifier,
+            Some(arg::VueTagModifier::ElseIf(_)) | Some(arg::VueTagModifier::Else)
+        );
+        if idx > 0 && !(previous_root_opened_if && is_if_chain_continuation) {
+            return Err(ParserError::new_at(
+                p,
+                &tag.name,
+                "component template should contain exactly one root element, use v-if, v-else-if and v-else to conditionally render a root element",
+            ));
+        }
+        previous_root_opened_if = matches!(
+            tag.args.modifier,
+            Some(arg::VueTagModifier::If(_)) | Some(arg::VueTagModifier::ElseIf(_))
+        );
+
+        if let TagType::Open(TagKind::Slot) | TagType::OpenAndClose(TagKind::Slot) = tag.type_ {
+            return Err(ParserError::new_at(
+                p,
+                &tag.name,
+                "cannot use <slot> as component root element because it may contain multiple nodes",
+            ));
+        }
+        if tag.name.eq(p, "template".chars()) {
+            return Err(ParserError::new_at(
+                p,
+                &tag.name,
+                "cannot use <template> as component root element because it may contain multiple nodes",
+            ));
+        }
+        if root.is_v_for() {
+            return Err(ParserError::new_at(
+                p,
+                &tag.name,
+                "cannot use v-for on the component root element because it renders multiple elements",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub enum Child {
     Tag(Tag, Vec<Child>),
     Text(SourceLocation),
-    Var(String),
+    // The compiled javascript of a {{ var }} and the location of the var its javascript
+    Var(String, SourceLocation),
+    // The contents of a html comment, only used when the comments compiler option is enabled
+    Comment(SourceLocation),
 }
 
 struct ParseChildrenResult {
@@ -216,7 +290,7 @@ impl Child {
         let mut children_with_v_slot = 0usize;
 
         loop {
-            let (text_node, compile_now) = Self::compile_text_node(p)?;
+            let (text_node, compile_now) = Self::compile_text_node(p, None)?;
             if let Some(node) = text_node {
                 inside_v_if = false;
                 resp.push(node);
@@ -226,12 +300,29 @@ impl Child {
                 CompileAfterTextNode::Tag => {
                     let mut tag = parse_tag(p, inside_v_if)?;
 
+                    if let TagType::Comment = tag.type_ {
+                        // Comments are only kept if enabled and never at the root of the template
+                        // like vue-template-compiler does
+                        if p.options.comments && !parents_tag_names.is_empty() {
+                            resp.push(Self::Comment(tag.name));
+                        }
+                        continue;
+                    }
+
                     if let Some(modifier) = tag.args.modifier.as_ref() {
                         inside_v_if = match modifier {
                             arg::VueTagModifier::If(_) => true,
                             arg::VueTagModifier::ElseIf(_) => true,
                             _ => false,
                         };
+
+                        if let arg::VueTagModifier::ElseIf(_) | arg::VueTagModifier::Else = modifier
+                        {
+                            // Comments between a v-if and v-else are dropped
+                            while let Some(Self::Comment(_)) = resp.last() {
+                                resp.pop();
+                            }
+                        }
                     } else {
                         inside_v_if = false;
                     }
@@ -267,21 +358,16 @@ impl Child {
                                 });
                             }
                         }
+                        TagType::Open(_) if is_raw_text_element(p, &tag.name) => {
+                            tag.push_local_variables(p);
+                            let children_result = Self::parse_raw_text(p, &tag.name);
+                            tag.pop_local_variables(p);
+                            resp.push(Self::Tag(tag, children_result?));
+                        }
                         TagType::Open(_) => {
                             parents_tag_names.push(tag.name.clone());
 
-                            let local_variables = tag.args.new_local_variables.as_ref();
-
-                            // Add the new local variables if there where some
-                            if let Some(new_local_variables) = local_variables {
-                                for var_name in new_local_variables {
-                                    if let Some(count) = p.local_variables.get_mut(var_name) {
-                                        *count += 1;
-                                    } else {
-                                        p.local_variables.insert(var_name.clone(), 1);
-                                    }
-                                }
-                            }
+                            tag.push_local_variables(p);
 
                             let compile_children_result =
                                 Self::parse_children(p, parents_tag_names);
@@ -289,18 +375,7 @@ impl Child {
                             let tag_name = parents_tag_names.pop().unwrap();
                             let compiled_children = compile_children_result?;
 
-                            // Remove the local variables we above inserted
-                            if let Some(new_local_variables) = local_variables {
-                                for var_name in new_local_variables {
-                                    if let Some(count) = p.local_variables.get_mut(var_name) {
-                                        if *count == 1 {
-                                            p.local_variables.remove(var_name);
-                                        } else {
-                                            *count -= 1;
-                                        }
-                                    }
-                                }
-                            }
+                            tag.pop_local_variables(p);
 
                             if compiled_children.children_with_v_slot > 0 {
                                 tag.args.has_js_component_args = true;
@@ -334,18 +409,46 @@ impl Child {
         }
     }
 
+    // Parses the contents of a raw text element like <textarea> until its closing tag
+    // The contents might only contain text and vars, other tags are seen as text
+    fn parse_raw_text(p: &mut Parser, tag_name: &SourceLocation) -> Result<Vec<Self>, ParserError> {
+        let mut closing_tag: Vec<char> = vec!['<', '/'];
+        closing_tag.extend(tag_name.chars(p));
+
+        let mut resp: Vec<Child> = Vec::new();
+        loop {
+            let (text_node, compile_now) = Self::compile_text_node(p, Some(&closing_tag))?;
+            if let Some(node) = text_node {
+                resp.push(node);
+            }
+
+            match compile_now {
+                CompileAfterTextNode::Tag => {
+                    // This is the closing tag of the raw text element
+                    parse_tag(p, false)?;
+                    return Ok(resp);
+                }
+                CompileAfterTextNode::Var => resp.push(Self::parse_var(p)?),
+            }
+        }
+    }
+
+    // Parses text until the next var or tag
+    // If raw_text_closing_tag is set only that closing tag ends the text (see parse_raw_text)
     fn compile_text_node(
         p: &mut Parser,
+        raw_text_closing_tag: Option<&[char]>,
     ) -> Result<(Option<Self>, CompileAfterTextNode), ParserError> {
         let text_node_start = p.current_char;
         let mut only_spaces = true;
+        let open_delimiter: Vec<char> = p.options.delimiters.0.chars().collect();
 
-        let gen_resp = |p: &mut Parser, only_spaces: bool| {
+        let gen_resp = |text_node_end: usize, only_spaces: bool| {
             if only_spaces {
                 // We do not care about strings with only spaces
                 None
             } else {
-                let resp = SourceLocation(text_node_start, p.current_char - 1);
+                let resp = SourceLocation(text_node_start, text_node_end);
                 if resp.is_empty() {
                     None
                 } else {
@@ -355,16 +458,35 @@ impl Child {
         };
 
         loop {
-            match p.must_read_one()? {
-                '<' => return Ok((gen_resp(p, only_spaces), CompileAfterTextNode::Tag)),
-                '{' => {
-                    if let Some(c) = p.seek_one() {
-                        if c == '{' {
-                            let resp = gen_resp(p, only_spaces);
-                            p.current_char += 1;
-                            return Ok((resp, CompileAfterTextNode::Var));
-                        }
+            if p.next_is(&open_delimiter) {
+                let resp = gen_resp(p.current_char, only_spaces);
+                p.current_char += open_delimiter.len();
+                return Ok((resp, CompileAfterTextNode::Var));
+            }
+
+            if let Some(closing_tag) = raw_text_closing_tag {
+                // Like in parse_tag the tag name ends at a space, > or / so </textareax> is text
+                let after_closing_tag = p.current_char + closing_tag.len();
+                let is_closing_tag = p.next_is(closing_tag)
+                    && p.source_chars[..p.source_chars_len]
+                        .get(after_closing_tag)
+                        .is_some_and(|c| *c == '>' || *c == '/' || is_space(*c));
+                if !is_closing_tag {
+                    if !is_space(p.must_read_one()?) {
+                        only_spaces = false;
                     }
+                    continue;
+                }
+            }
+
+            match p.must_read_one()? {
+                // Like the html tokenizer a < only starts a tag if followed by a letter, / or !
+                // Otherwise it's part of the text (if a < b)
+                '<' if matches!(p.seek_one_or_null(), 'a'..='z' | 'A'..='Z' | '/' | '!') => {
+                    return Ok((
+                        gen_resp(p.current_char - 1, only_spaces),
+                        CompileAfterTextNode::Tag,
+                    ))
                 }
                 c if only_spaces && is_space(c) => {}
                 _ => only_spaces = false,
@@ -373,10 +495,9 @@ impl Child {
     }
 
     fn parse_var(p: &mut Parser) -> Result<Self, ParserError> {
-        let start = p.current_char;
-        let global_vars = js::parse_template_var(p)?;
-        let var = SourceLocation(start, p.current_char - 2);
-        Ok(Self::Var(js::add_vm_references(p, &var, &global_vars)))
+        let var = js::parse_template_var(p)?;
+        let js = js::compile_template_js(p, &var, js::TemplateJs::Expression)?;
+        Ok(Self::Var(js, var))
     }
 
     pub fn is_v_else_or_else_if(&self) -> bool {
@@ -415,6 +536,36 @@ pub struct Tag {
     pub args: VueTagArgs,
 }
 
+impl Tag {
+    // Add the local variables set by this tag (for example by a v-for) so they are not prefixed with _vm.
+    fn push_local_variables(&self, p: &mut Parser) {
+        if let Some(new_local_variables) = self.args.new_local_variables.as_ref() {
+            for var_name in new_local_variables {
+                if let Some(count) = p.local_variables.get_mut(var_name) {
+                    *count += 1;
+                } else {
+                    p.local_variables.insert(var_name.clone(), 1);
+                }
+            }
+        }
+    }
+
+    // Remove the local variables inserted by push_local_variables
+    fn pop_local_variables(&self, p: &mut Parser) {
+        if let Some(new_local_variables) = self.args.new_local_variables.as_ref() {
+            for var_name in new_local_variables {
+                if let Some(count) = p.local_variables.get_mut(var_name) {
+                    if *count == 1 {
+                        p.local_variables.remove(var_name);
+                    } else {
+                        *count -= 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TagKind {
     HtmlElement,
@@ -422,6 +573,7 @@ pub enum TagKind {
     Slot,
 }
 
+// Note that SVG and MathML elements are case sensitive (linearGradient)
 pub fn tag_name_kind(parser: &Parser, tag_name: &SourceLocation) -> TagKind {
     let slot_and_html_elements = vec![
         // Check for slot
@@ -553,6 +705,100 @@ pub fn tag_name_kind(parser: &Parser, tag_name: &SourceLocation) -> TagKind {
         "var".chars(),
         "video".chars(),
         "wbr".chars(),
+        // SVG elements
+        "animate".chars(),
+        "animateMotion".chars(),
+        "animateTransform".chars(),
+        "circle".chars(),
+        "clipPath".chars(),
+        "defs".chars(),
+        "desc".chars(),
+        "discard".chars(),
+        "ellipse".chars(),
+        "feBlend".chars(),
+        "feColorMatrix".chars(),
+        "feComponentTransfer".chars(),
+        "feComposite".chars(),
+        "feConvolveMatrix".chars(),
+        "feDiffuseLighting".chars(),
+        "feDisplacementMap".chars(),
+        "feDistantLight".chars(),
+        "feDropShadow".chars(),
+        "feFlood".chars(),
+        "feFuncA".chars(),
+        "feFuncB".chars(),
+        "feFuncG".chars(),
+        "feFuncR".chars(),
+        "feGaussianBlur".chars(),
+        "feImage".chars(),
+        "feMerge".chars(),
+        "feMergeNode".chars(),
+        "feMorphology".chars(),
+        "feOffset".chars(),
+        "fePointLight".chars(),
+        "feSpecularLighting".chars(),
+        "feSpotLight".chars(),
+        "feTile".chars(),
+        "feTurbulence".chars(),
+        "filter".chars(),
+        "foreignObject".chars(),
+        "g".chars(),
+        "image".chars(),
+        "line".chars(),
+        "linearGradient".chars(),
+        "marker".chars(),
+        "mask".chars(),
+        "metadata".chars(),
+        "mpath".chars(),
+        "path".chars(),
+        "pattern".chars(),
+        "polygon".chars(),
+        "polyline".chars(),
+        "radialGradient".chars(),
+        "rect".chars(),
+        "set".chars(),
+        "stop".chars(),
+        "switch".chars(),
+        "symbol".chars(),
+        "text".chars(),
+        "textPath".chars(),
+        "tspan".chars(),
+        "use".chars(),
+        "view".chars(),
+        // MathML elements
+        "math".chars(),
+        "maction".chars(),
+        "annotation".chars(),
+        "annotation-xml".chars(),
+        "menclose".chars(),
+        "merror".chars(),
+        "mfenced".chars(),
+        "mfrac".chars(),
+        "mi".chars(),
+        "mmultiscripts".chars(),
+        "mn".chars(),
+        "mo".chars(),
+        "mover".chars(),
+        "mpadded".chars(),
+        "mphantom".chars(),
+        "mprescripts".chars(),
+        "mroot".chars(),
+        "mrow".chars(),
+        "ms".chars(),
+        "mspace".chars(),
+        "msqrt".chars(),
+        "mstyle".chars(),
+        "msub".chars(),
+        "msubsup".chars(),
+        "msup".chars(),
+        "mtable".chars(),
+        "mtd".chars(),
+        "mtext".chars(),
+        "mtr".chars(),
+        "munder".chars(),
+        "munderover".chars(),
+        "none".chars(),
+        "semantics".chars(),
     ];
 
     match tag_name.eq_some(parser, false, slot_and_html_elements) {
@@ -562,6 +808,22 @@ pub fn tag_name_kind(parser: &Parser, tag_name: &SourceLocation) -> TagKind {
     }
 }
 
+// Returns true for elements that can only contain text (the html raw text and escapable raw text elements)
+fn is_raw_text_element(p: &Parser, tag_name: &SourceLocation) -> bool {
+    tag_name
+        .eq_some(
+            p,
+            false,
+            vec![
+                "script".chars(),
+                "style".chars(),
+                "textarea".chars(),
+                "title".chars(),
+            ],
+        )
+        .is_some()
+}
+
 #[derive(Debug, Clone)]
 pub enum StaticOrJS {
     Non,
@@ -587,15 +849,23 @@ pub struct VueTagArgs {
     pub modifier: Option<arg::VueTagModifier>,
     pub has_js_component_args: bool,
 
+    // The static class attribute
+    // class="foo bar"
+    pub static_class: Option<String>,
+
     // Same API as `v-bind:class`, accepting either
     // a string, object, or array of strings and objects.
     // {foo: true, bar: false}
-    pub class: Option<StaticOrJS>,
+    pub class: Option<String>,
+
+    // The declarations of the static style attribute
+    // style="color: red; font-size: 14px"
+    pub static_style: Option<Vec<(String, String)>>,
 
     // Same API as `v-bind:style`, accepting either
     // a string, object, or array of objects.
     //{ color: 'red', fontSize: '14px'}
-    pub style: Option<StaticOrJS>,
+    pub style: Option<String>,
 
     // Normal HTML attributes
     // OR
@@ -655,7 +925,9 @@ impl VueTagArgs {
             new_local_variables: None,
             modifier: None,
             has_js_component_args: false,
+            static_class: None,
             class: None,
+            static_style: None,
             style: None,
             attrs_or_props: None,
             dom_props: None,
@@ -689,8 +961,19 @@ impl VueTagArgs {
 
     fn set_default_or_bind(&mut self, key: &str, value: StaticOrJS) -> Result<(), ParserError> {
         match key {
-            "class" => self.class = Some(value),
-            "style" => self.style = Some(value),
+            // The static and bound class / style are kept apart so vue can merge them
+            "class" => match value {
+                StaticOrJS::Non => {}
+                StaticOrJS::Static(value) => self.static_class = Some(value),
+                StaticOrJS::Bind(value) => self.class = Some(value),
+            },
+            "style" => match value {
+                StaticOrJS::Non => {}
+                StaticOrJS::Static(value) => {
+                    self.static_style = Some(style::parse_inline_style(&value))
+                }
+                StaticOrJS::Bind(value) => self.style = Some(value),
+            },
             "key" => self.key = Some(value),
             "ref" => self.ref_ = Some(value),
             _ => add_or_set(&mut self.attrs_or_props, (key.to_string(), value)),
@@ -0,0 +1,417 @@
+use super::super::utils::is_space;
+use super::super::{Parser, ParserError, SourceLocation};
+use super::arg::{self, ArgsSyntax, VueTagModifier};
+use super::{tag_name_kind, Child, StaticOrJS, Tag, TagType, VueTagArgs};
+
+/*
+    A pug (https://pugjs.org) front end for <template lang="pug">
+    This produces the same children tree as the html parser so the to_js code is shared
+
+    Supported:
+    - Tags nested by indentation
+    - Class and id shorthands (`a.foo#bar`, `.foo` is an implicit div)
+    - Attributes (`a(href="/", :title="title" disabled)`), unquoted values are bound (`a(href=url)`)
+    - Inline text (`p Hello {{ name }}`)
+    - Piped text (`| Hello {{ name }}`)
+    - Block text (`p.` followed by indented lines of text)
+    - Block expansion (`li: a(href="/") Home`)
+    - Self closing tags (`img/`)
+    - Comments (`// ..` and `//- ..`)
+*/
+
+// compile is expected to be called right after the <template lang="pug"> tag
+pub fn compile(p: &mut Parser) -> Result<Vec<Child>, ParserError> {
+    let start = p.current_char;
+    let template_closure = p.look_for("</template>".chars().collect())?;
+    p.current_char = start;
+
+    let mut pug = PugParser {
+        end: template_closure.0,
+    };
+    let children = pug.parse_block(p, None)?;
+
+    p.current_char = template_closure.1;
+    Ok(children)
+}
+
+struct PugParser {
+    // The location of the </template> closure, the pug parser never reads beyond this point
+    end: usize,
+}
+
+impl PugParser {
+    // Parses all lines that are indented more than the parent_indent
+    fn parse_block(
+        &mut self,
+        p: &mut Parser,
+        parent_indent: Option<usize>,
+    ) -> Result<Vec<Child>, ParserError> {
+        let mut children: Vec<Child> = Vec::new();
+        let mut block_indent: Option<usize> = None;
+        let mut inside_v_if = false;
+        let mut last_line_was_text = false;
+
+        while let Some((indent, content_start)) = self.next_line(p) {
+            if let Some(parent_indent) = parent_indent {
+                if indent <= parent_indent {
+                    break;
+                }
+            }
+
+            p.current_char = content_start + 1;
+            match block_indent {
+                None => block_indent = Some(indent),
+                Some(block_indent) if block_indent == indent => {}
+                Some(block_indent) if indent > block_indent => {
+                    return Err(ParserError::new(p, "unexpected indentation"))
+                }
+                Some(_) => return Err(ParserError::new(p, "inconsistent indentation")),
+            }
+            p.current_char = content_start;
+
+            if p.next_is(&['/', '/']) {
+                // This is a comment, the lines nested below the comment are also part of the comment
+                p.current_char += 2;
+                let comment_start = p.current_char;
+                let buffered = p.seek_one_or_null() != '-';
+
+                self.skip_line(p);
+                self.skip_nested_lines(p, indent);
+
+                // Like in html templates comments at the root are always dropped
+                if buffered && p.options.comments && parent_indent.is_some() {
+                    let mut comment_end = p.current_char;
+                    while comment_end > comment_start && is_space(p.source_chars[comment_end - 1]) {
+                        comment_end -= 1;
+                    }
+                    children.push(Child::Comment(SourceLocation(comment_start, comment_end)));
+                    last_line_was_text = false;
+                }
+                continue;
+            }
+
+            if p.next_is(&"doctype".chars().collect::<Vec<char>>()) {
+                self.skip_line(p);
+                continue;
+            }
+
+            if p.next_is(&['|']) {
+                // Piped text
+                p.current_char += 1;
+                if p.seek_one_or_null() == ' ' {
+                    p.current_char += 1;
+                }
+
+                if last_line_was_text {
+                    children.push(Child::Text(p.add_virtual_source("\n")));
+                }
+                self.parse_text(p, &mut children)?;
+                self.skip_line(p);
+
+                inside_v_if = false;
+                last_line_was_text = true;
+                continue;
+            }
+
+            let child = self.parse_tag(p, indent, inside_v_if)?;
+            if child.is_v_else_or_else_if() {
+                // Comments between a v-if and v-else are dropped
+                while let Some(Child::Comment(_)) = children.last() {
+                    children.pop();
+                }
+            }
+            if let Child::Tag(tag, _) = &child {
+                inside_v_if = matches!(
+                    tag.args.modifier.as_ref(),
+                    Some(VueTagModifier::If(_)) | Some(VueTagModifier::ElseIf(_))
+                );
+            }
+            children.push(child);
+            last_line_was_text = false;
+        }
+
+        Ok(children)
+    }
+
+    // Parses a tag with its content, the parser is expected to be at the first character of the tag
+    fn parse_tag(
+        &mut self,
+        p: &mut Parser,
+        indent: usize,
+        v_else_allowed: bool,
+    ) -> Result<Child, ParserError> {
+        let name = match p.seek_one_or_null() {
+            'a'..='z' | 'A'..='Z' => self.parse_name(p),
+            '.' | '#' => p.add_virtual_source("div"),
+            c => {
+                p.current_char += 1;
+                return Err(ParserError::new(
+                    p,
+                    format!("unexpected character '{}', expected a tag", c),
+                ));
+            }
+        };
+
+        let kind = tag_name_kind(p, &name);
+        let mut args = VueTagArgs::new();
+        let mut classes: Vec<String> = Vec::new();
+        let mut block_text = false;
+
+        loop {
+            match p.seek_one_or_null() {
+                '.' | '#' => {
+                    let shorthand_kind = p.must_read_one()?;
+                    let value = self.parse_name(p);
+                    if value.is_empty() {
+                        if shorthand_kind == '.' {
+                            // The tag content is a block of text (`p.`)
+                            block_text = true;
+                            break;
+                        }
+                        return Err(ParserError::new(p, "expected an id after #"));
+                    }
+
+                    if shorthand_kind == '.' {
+                        classes.push(value.string(p));
+                    } else {
+                        args.set_default_or_bind("id", StaticOrJS::Static(value.string(p)))?;
+                        args.has_js_component_args = true;
+                    }
+                }
+                '(' => {
+                    p.current_char += 1;
+                    self.parse_args(p, &mut args, v_else_allowed, &kind)?;
+                }
+                _ => break,
+            }
+        }
+
+        if !classes.is_empty() {
+            let mut class = classes.join(" ");
+            if let Some(other) = args.static_class.take() {
+                class.push(' ');
+                class.push_str(&other);
+            }
+            args.static_class = Some(class);
+            args.has_js_component_args = true;
+        }
+
+        let self_closing = p.seek_one_or_null() == '/';
+        if self_closing {
+            p.current_char += 1;
+        }
+
+        let mut tag = Tag {
+            type_: if self_closing {
+                TagType::OpenAndClose(kind)
+            } else {
+                TagType::Open(kind)
+            },
+            name,
+            args,
+        };
+        let mut children: Vec<Child> = Vec::new();
+
+        tag.push_local_variables(p);
+        let children_result = self.parse_tag_content(p, indent, block_text, &mut children);
+        tag.pop_local_variables(p);
+        children_result?;
+
+        let children_with_slot = children
+            .iter()
+            .filter(
+                |child| matches!(child, Child::Tag(child_tag, _) if child_tag.args.slot.is_some()),
+            )
+            .count();
+        if children_with_slot > 0 {
+            tag.args.has_js_component_args = true;
+            tag.args.children_with_slot = children_with_slot;
+        }
+
+        Ok(Child::Tag(tag, children))
+    }
+
+    // Parses everything after the tag name, shorthands and arguments
+    fn parse_tag_content(
+        &mut self,
+        p: &mut Parser,
+        indent: usize,
+        block_text: bool,
+        children: &mut Vec<Child>,
+    ) -> Result<(), ParserError> {
+        match p.seek_one_or_null() {
+            ':' => {
+                // Block expansion (`li: a Home`), the lines nested below are part of the inner tag
+                p.current_char += 1;
+                while let ' ' | '\t' = p.seek_one_or_null() {
+                    p.current_char += 1;
+                }
+                children.push(self.parse_tag(p, indent, false)?);
+                return Ok(());
+            }
+            ' ' | '\t' => {
+                // Inline text (`p Hello world`)
+                p.current_char += 1;
+                self.parse_text(p, children)?;
+            }
+            _ => {}
+        }
+
+        self.expect_line_end(p)?;
+
+        if block_text {
+            self.parse_text_block(p, indent, children)
+        } else {
+            children.append(&mut self.parse_block(p, Some(indent))?);
+            Ok(())
+        }
+    }
+
+    // Parses the tag arguments, the parser is expected to be after the (
+    fn parse_args(
+        &mut self,
+        p: &mut Parser,
+        args: &mut VueTagArgs,
+        v_else_allowed: bool,
+        kind: &super::TagKind,
+    ) -> Result<(), ParserError> {
+        loop {
+            let mut c = p.must_read_one()?;
+            if let Some(next_c) = arg::try_parse(p, c, args, v_else_allowed, kind, ArgsSyntax::Pug)?
+            {
+                c = next_c;
+            }
+
+            match c {
+                ')' => return Ok(()),
+                ',' => {}
+                c if is_space(c) => {}
+                c => return Err(ParserError::new(p, format!("unexpected character '{}'", c))),
+            }
+        }
+    }
+
+    // Parses text until the end of the line, the text might contain vars ({{ foo }})
+    fn parse_text(&mut self, p: &mut Parser, children: &mut Vec<Child>) -> Result<(), ParserError> {
+        let open_delimiter: Vec<char> = p.options.delimiters.0.chars().collect();
+        let mut text_start = p.current_char;
+
+        loop {
+            if self.at_line_end(p) {
+                push_text(p, children, SourceLocation(text_start, p.current_char));
+                return Ok(());
+            }
+
+            if p.next_is(&open_delimiter) {
+                push_text(p, children, SourceLocation(text_start, p.current_char));
+                p.current_char += open_delimiter.len();
+                children.push(Child::parse_var(p)?);
+                text_start = p.current_char;
+            } else {
+                p.current_char += 1;
+            }
+        }
+    }
+
+    // Parses the lines of a block of text (`p.`)
+    fn parse_text_block(
+        &mut self,
+        p: &mut Parser,
+        parent_indent: usize,
+        children: &mut Vec<Child>,
+    ) -> Result<(), ParserError> {
+        let mut is_first_line = true;
+        while let Some((indent, content_start)) = self.next_line(p) {
+            if indent <= parent_indent {
+                break;
+            }
+
+            if !is_first_line {
+                children.push(Child::Text(p.add_virtual_source("\n")));
+            }
+            is_first_line = false;
+
+            p.current_char = content_start;
+            self.parse_text(p, children)?;
+            self.skip_line(p);
+        }
+        Ok(())
+    }
+
+    // Parses a tag, class or id name
+    fn parse_name(&mut self, p: &mut Parser) -> SourceLocation {
+        let start = p.current_char;
+        while p.current_char < self.end {
+            match p.source_chars[p.current_char] {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => p.current_char += 1,
+                _ => break,
+            }
+        }
+        SourceLocation(start, p.current_char)
+    }
+
+    // Returns the indentation and the first non space character of the next non empty line
+    // The parser is left at the start of that line
+    fn next_line(&mut self, p: &mut Parser) -> Option<(usize, usize)> {
+        loop {
+            let line_start = p.current_char;
+            let mut idx = line_start;
+            while idx < self.end && (p.source_chars[idx] == ' ' || p.source_chars[idx] == '\t') {
+                idx += 1;
+            }
+
+            if idx >= self.end {
+                p.current_char = self.end;
+                return None;
+            }
+
+            match p.source_chars[idx] {
+                // This line is empty
+                '\n' | '\r' => p.current_char = idx + 1,
+                _ => return Some((idx - line_start, idx)),
+            }
+        }
+    }
+
+    fn at_line_end(&self, p: &Parser) -> bool {
+        p.current_char >= self.end || matches!(p.source_chars[p.current_char], '\n' | '\r')
+    }
+
+    // Moves the parser to the start of the next line
+    fn skip_line(&mut self, p: &mut Parser) {
+        while !self.at_line_end(p) {
+            p.current_char += 1;
+        }
+        if p.current_char < self.end {
+            p.current_char += 1;
+        }
+    }
+
+    fn skip_nested_lines(&mut self, p: &mut Parser, parent_indent: usize) {
+        while let Some((indent, _)) = self.next_line(p) {
+            if indent <= parent_indent {
+                return;
+            }
+            self.skip_line(p);
+        }
+    }
+
+    // Makes sure only spacing is left on the current line and moves to the next line
+    fn expect_line_end(&mut self, p: &mut Parser) -> Result<(), ParserError> {
+        while !self.at_line_end(p) {
+            let c = p.source_chars[p.current_char];
+            p.current_char += 1;
+            if !is_space(c) {
+                return Err(ParserError::new(p, format!("unexpected character '{}'", c)));
+            }
+        }
+        self.skip_line(p);
+        Ok(())
+    }
+}
+
+fn push_text(p: &Parser, children: &mut Vec<Child>, location: SourceLocation) {
+    if location.chars(p).iter().any(|c| !is_space(*c)) {
+        children.push(Child::Text(location));
+    }
+}
@@ -1,6 +1,6 @@
 use super::super::utils::write_str;
-use super::super::{utils, Parser, SourceLocation};
-use super::{arg::VueTagModifier, Child, StaticOrJS, VueTagArgs};
+use super::super::{js, style, utils, Parser, ParserError, SourceLocation};
+use super::{arg::VueTagModifier, validate_root, Child, StaticOrJS, VueTagArgs};
 use super::{TagKind, TagType};
 use std::slice::Iter;
 
@@ -14,22 +14,47 @@ c.staticRenderFns = [];
 c.render = function(c) {
     const _vm = this;
     const _h = _vm.$createElement;
-    const _c = _vm._self._c || _h;
-    return ";
+    const _c = _vm._self._c || _h;";
 
-pub fn template_to_js(p: &Parser, resp: &mut Vec<char>) {
+// Functional components get the render context as second argument
+// https://v2.vuejs.org/v2/guide/render-function.html#Functional-Components
+const FUNCTIONAL_CONF: &str = "
+c._compiled = true;
+c.functional = true;
+c.staticRenderFns = [];
+c.render = function(_h, _vm) {
+    const _c = _vm._c;";
+
+pub fn template_to_js(p: &Parser, resp: &mut Vec<char>) -> Result<(), ParserError> {
     let template = match p.template.as_ref() {
         Some(t) => t,
-        None => return,
+        None => return Ok(()),
     };
 
-    resp.append(&mut DEFAULT_CONF.chars().collect());
+    if template.functional {
+        resp.append(&mut FUNCTIONAL_CONF.chars().collect());
+    } else {
+        // Functional components might return multiple root nodes, other components can not
+        validate_root(p, &template.content)?;
+        resp.append(&mut DEFAULT_CONF.chars().collect());
+    }
+
+    if p.script_setup.is_some() && !template.functional {
+        write_str("\n    const _setup = _vm._self._setupProxy;", resp);
+    }
+    if p.options.lower_optional_chaining {
+        write_str("\n    var ", resp);
+        write_str(js::TEMP_VARIABLES, resp);
+        resp.push(';');
+    }
+    write_str("\n    return ", resp);
 
     match template.content.len() {
         0 => {
             write_str("[]", resp);
         }
-        1 => {
+        // Multiple roots of a non functional template are always a v-if chain and thus one expression
+        _ if template.content.len() == 1 || !template.functional => {
             children_to_js(&template.content, p, resp, false);
         }
         _ => {
@@ -39,7 +64,56 @@ pub fn template_to_js(p: &Parser, resp: &mut Vec<char>) {
         }
     }
 
-    resp.append(&mut "\n};".chars().collect())
+    resp.append(&mut "\n};".chars().collect());
+    Ok(())
+}
+
+// Keeps the css custom properties of the v-bind() calls within the styles in sync with the component
+// The properties are set on the root element once mounted and updated when the bound values change
+pub fn css_vars_to_js(p: &Parser, id: &str, resp: &mut Vec<char>) -> Result<(), ParserError> {
+    if p.css_vars.is_empty() {
+        return Ok(());
+    }
+
+    write_str("\nconst _cssVars = function(_vm) {", resp);
+    if p.script_setup.is_some() {
+        write_str("\n    const _setup = _vm._self._setupProxy;", resp);
+    }
+    if p.options.lower_optional_chaining {
+        write_str("\n    var ", resp);
+        write_str(js::TEMP_VARIABLES, resp);
+        resp.push(';');
+    }
+    write_str("\n    return {", resp);
+    for css_var in p.css_vars.iter() {
+        let name = style::css_var_name(id, &css_var.string(p));
+        let value = js::compile_template_js(p, css_var, js::TemplateJs::Expression)?;
+        write_str("\n        \"", resp);
+        write_str(&name, resp);
+        write_str("\": (", resp);
+        write_str(&value, resp);
+        write_str("),", resp);
+    }
+    write_str(
+        "
+    };
+};
+const _setCssVars = function(_vm, vars) {
+    const el = _vm.$el;
+    if (el && el.style) {
+        for (const key in vars) el.style.setProperty(key, vars[key]);
+    }
+};
+c.mounted = [function() {
+    const _vm = this;
+    _vm.$watch(function() { return _cssVars(_vm); }, function(vars) { _setCssVars(_vm, vars); }, { immediate: true });
+}].concat(c.mounted || []);
+c.updated = [function() {
+    _setCssVars(this, _cssVars(this));
+}].concat(c.updated || []);",
+        resp,
+    );
+    Ok(())
 }
 
 pub struct AddChildrenResult {
@@ -47,7 +121,7 @@ pub struct AddChildrenResult {
 }
 
 pub fn children_to_js(
-    children: &Vec<Child>,
+    children: &[Child],
     p: &Parser,
     resp: &mut Vec<char>,
     filter_out_tags_with_slot_attr: bool,
@@ -73,7 +147,7 @@ pub fn children_to_js(
             // _vm._v("foo bar")
             // Or in case of text mixed with vars:
             // _vm._v("foo bar " + _vm._s(_vm.some_var) + "!")
-            Child::Var(var) => {
+            Child::Var(var, _) => {
                 write_str("_vm._v(", resp);
                 write_vue_js_var(var, resp);
                 let might_next_child = concat_next_text_and_vars(p, &mut children_iter, resp);
@@ -149,11 +223,11 @@ fn concat_next_text_and_vars<'a>(
                     resp.push('+');
                     write_text_quote(p, location, resp);
                 }
-                Child::Var(var) => {
+                Child::Var(var, _) => {
                     resp.push('+');
                     write_vue_js_var(var, resp);
                 }
-                Child::Tag(_, _) => {
+                Child::Tag(_, _) | Child::Comment(_) => {
                     return Some(child);
                 }
             }
@@ -289,9 +363,6 @@ pub fn child_to_js(
 
                     children_len -= tag.args.children_with_slot;
 
-                    write_str("_c('", resp);
-                    tag.name.write_to_vec_escape(p, resp, '\'', '\\');
-                    resp.push('\'');
                     artifacts.is_custom_component = match &tag.type_ {
                         TagType::Open(kind) | TagType::OpenAndClose(kind) => match kind {
                             TagKind::Slot => true,
@@ -300,6 +371,20 @@ pub fn child_to_js(
                         },
                         _ => true,
                     };
+                    // Components imported within <script setup> are used directly instead of being resolved by name
+                    let setup_binding = if artifacts.is_custom_component {
+                        js::setup_binding_for_tag(p, &tag.name.string(p))
+                    } else {
+                        None
+                    };
+                    if let Some(setup_binding) = setup_binding {
+                        write_str("_c(_setup.", resp);
+                        write_str(&setup_binding, resp);
+                    } else {
+                        write_str("_c('", resp);
+                        tag.name.write_to_vec_escape(p, resp, '\'', '\\');
+                        resp.push('\'');
+                    }
                     if tag.args.has_js_component_args {
                         resp.push(',');
                         vue_tag_args_to_js(
@@ -344,14 +429,36 @@ pub fn child_to_js(
             write_text_quote(p, location, resp);
             resp.push(')');
         }
-        Child::Var(var) => {
+        Child::Var(var, _) => {
             write_vue_js_var(var, resp);
         }
+        Child::Comment(location) => {
+            // Writes: _vm._e("comment text")
+            write_str("_vm._e(", resp);
+            write_comment_quote(p, location, resp);
+            resp.push(')');
+        }
     };
 
     artifacts
 }
 
+fn write_comment_quote(p: &Parser, location: &SourceLocation, resp: &mut Vec<char>) {
+    resp.push('"');
+    for c in location.chars(p) {
+        match c {
+            '"' | '\\' => {
+                resp.push('\\');
+                resp.push(*c);
+            }
+            '\n' => write_str("\\n", resp),
+            '\r' => write_str("\\r", resp),
+            c => resp.push(*c),
+        }
+    }
+    resp.push('"');
+}
+
 fn write_vue_js_var(var: &str, resp: &mut Vec<char>) {
     // Writes _vm._s(_vm.some_var)
     write_str("_vm._s(", resp);
@@ -389,7 +496,7 @@ fn write_text_quote(p: &Parser, location: &SourceLocation, resp: &mut Vec<char>)
 }
 
 pub fn vue_tag_args_to_js(
-    children: &Vec<Child>,
+    children: &[Child],
     args: &VueTagArgs,
     dest: &mut Vec<char>,
     is_custom_component: bool,
@@ -398,36 +505,28 @@ pub fn vue_tag_args_to_js(
     dest.push('{');
     let mut object_entries = CommaSeparatedEntries::new();
 
+    if let Some(static_class) = args.static_class.as_ref() {
+        object_entries.add(dest);
+        write_str("staticClass:", dest);
+        write_str_with_quotes(static_class, dest);
+    }
+
     if let Some(class) = args.class.as_ref() {
-        match class {
-            StaticOrJS::Non => {}
-            StaticOrJS::Static(value) => {
-                object_entries.add(dest);
-                write_str("staticClass:", dest);
-                write_str_with_quotes(value, dest);
-            }
-            StaticOrJS::Bind(value) => {
-                object_entries.add(dest);
-                write_str("class:", dest);
-                write_str(&value, dest);
-            }
-        };
+        object_entries.add(dest);
+        write_str("class:", dest);
+        write_str(class, dest);
+    }
+
+    if let Some(static_style) = args.static_style.as_ref() {
+        object_entries.add(dest);
+        write_str("staticStyle:", dest);
+        write_string_object(static_style, dest);
     }
 
     if let Some(style) = args.style.as_ref() {
-        match style {
-            StaticOrJS::Non => {}
-            StaticOrJS::Static(value) => {
-                object_entries.add(dest);
-                write_str("style:", dest);
-                write_str_with_quotes(value, dest);
-            }
-            StaticOrJS::Bind(value) => {
-                object_entries.add(dest);
-                write_str("style:", dest);
-                write_str(&value, dest);
-            }
-        }
+        object_entries.add(dest);
+        write_str("style:", dest);
+        write_str(style, dest);
     }
 
     if let Some(attrs) = args.attrs_or_props.as_ref() {
@@ -468,10 +567,8 @@ pub fn vue_tag_args_to_js(
             on_entries.add(dest);
 
             write_str_with_quotes(key, dest);
-            write_str(":$event=>{", dest);
-            write_str(&value, dest);
-
-            dest.push('}');
+            dest.push(':');
+            write_str(value, dest);
         }
 
         dest.push('}');
@@ -486,13 +583,8 @@ pub fn vue_tag_args_to_js(
             on_entries.add(dest);
 
             write_str_with_quotes(key, dest);
-            write_str(":$event=>{", dest);
-
-            for c in value.chars() {
-                dest.push(c);
-            }
-
-            dest.push('}');
+            dest.push(':');
+            write_str(value, dest);
         }
 
         dest.push('}');
@@ -624,6 +716,18 @@ fn write_static_or_js(value: &StaticOrJS, dest: &mut Vec<char>) {
     }
 }
 
+fn write_string_object(key_values: &[(String, String)], dest: &mut Vec<char>) {
+    dest.push('{');
+    let mut entries = CommaSeparatedEntries::new();
+    for (key, value) in key_values {
+        entries.add(dest);
+        write_str_with_quotes(key, dest);
+        dest.push(':');
+        write_str_with_quotes(value, dest);
+    }
+    dest.push('}');
+}
+
 fn write_object(key_values: &Vec<(String, StaticOrJS)>, dest: &mut Vec<char>) {
     dest.push('{');
     let mut entries = CommaSeparatedEntries::new();
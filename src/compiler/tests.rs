@@ -18,7 +18,7 @@ mod tests {
     }
     fn unwrap_var_child(children: &Vec<Child>, idx: usize) -> String {
         match children.get(idx).unwrap() {
-            Child::Var(var) => var.clone(),
+            Child::Var(var, _) => var.clone(),
             v => panic!("{:?}", v),
         }
     }
@@ -148,6 +148,227 @@ mod tests {
         );
     }
 
+    #[test]
+    fn custom_blocks() {
+        let result = Parser::new_and_parse(
+            "<template><div></div></template>
+            <i18n lang=\"json\" locale=\"en\" global>{ \"hello\": \"<b>Hello</b>\" }</i18n>
+            <docs># Docs</docs>",
+            "example",
+        )
+        .unwrap();
+
+        assert_eq!(result.custom_blocks.len(), 2);
+
+        let i18n = &result.custom_blocks[0];
+        assert_eq!(i18n.type_, "i18n");
+        assert_eq!(i18n.lang.as_deref(), Some("json"));
+        assert_eq!(
+            i18n.attrs,
+            vec![
+                (String::from("lang"), Some(String::from("json"))),
+                (String::from("locale"), Some(String::from("en"))),
+                (String::from("global"), None),
+            ]
+        );
+        assert_eq!(
+            i18n.content.string(&result),
+            "{ \"hello\": \"<b>Hello</b>\" }"
+        );
+
+        let docs = &result.custom_blocks[1];
+        assert_eq!(docs.type_, "docs");
+        assert!(docs.lang.is_none());
+        assert_eq!(docs.content.string(&result), "# Docs");
+    }
+
+    #[test]
+    fn src_blocks() {
+        let mut result = Parser::new_and_parse(
+            "<template src=\"./template.pug\"></template>
+            <script src=\"./script.ts\"></script>
+            <style src=\"./style.scss\" scoped></style>
+            <style src=\"./plain\" lang=\"less\"></style>",
+            "example",
+        )
+        .unwrap();
+
+        let script = result.script.as_ref().unwrap();
+        assert_eq!(script.src.as_deref(), Some("./script.ts"));
+        assert_eq!(script.lang.as_deref(), Some("ts"));
+        assert_eq!(script.content.string(&result), "");
+
+        assert_eq!(result.styles.len(), 2);
+        for (style, (src, lang, scoped)) in result
+            .styles
+            .iter()
+            .zip([("./style.scss", "scss", true), ("./plain", "less", false)])
+        {
+            match style {
+                Style::Normal(style) => {
+                    assert_eq!(style.src.as_deref(), Some(src));
+                    assert_eq!(style.lang.as_deref(), Some(lang));
+                    assert_eq!(style.scoped, scoped);
+                }
+                Style::DirectScopedCSS(_) => panic!("expected a normal style"),
+            }
+        }
+
+        let template = result.template.as_ref().unwrap();
+        assert_eq!(template.src.as_deref(), Some("./template.pug"));
+        assert_eq!(template.lang.as_deref(), Some("pug"));
+        assert!(template.content.is_empty());
+
+        result.compile_template_src("div\n  p {{ foo }}").unwrap();
+        let template = result.template.as_ref().unwrap();
+        assert_eq!(template.src.as_deref(), Some("./template.pug"));
+        assert_eq!(template.content.len(), 1);
+
+        let mut resp: Vec<char> = Vec::new();
+        template::to_js::template_to_js(&result, &mut resp).unwrap();
+        assert!(resp
+            .iter()
+            .collect::<String>()
+            .contains("_c('div',[_c('p',[_vm._v(_vm._s( _vm.foo ))])])"));
+
+        assert!(
+            Parser::new_and_parse("<script setup src=\"./setup.js\"></script>", "example").is_err()
+        );
+    }
+
+    #[test]
+    fn css_modules() {
+        let result = Parser::new_and_parse(
+            "<style module>.a {}</style>
+            <style module=\"classes\" scoped>.b {}</style>
+            <style scoped>.c {}</style>",
+            "example",
+        )
+        .unwrap();
+
+        assert_eq!(result.styles.len(), 3);
+        let modules: Vec<Option<&str>> = result
+            .styles
+            .iter()
+            .map(|style| match style {
+                Style::Normal(style) => style.module.as_deref(),
+                Style::DirectScopedCSS(_) => None,
+            })
+            .collect();
+        assert_eq!(modules, vec![Some("$style"), Some("classes"), None]);
+        // Scoped css modules are scoped by the plugin after being imported
+        match &result.styles[1] {
+            Style::Normal(style) => assert!(style.scoped),
+            Style::DirectScopedCSS(_) => panic!("expected a normal style"),
+        }
+
+        assert!(Parser::new_and_parse(
+            "<style module>.a {}</style><style module=\"$style\">.b {}</style>",
+            "example"
+        )
+        .is_err());
+
+        let err =
+            Parser::new_and_parse("<style :module=\"name\">.a {}</style>", "example").unwrap_err();
+        assert_eq!(
+            err.message,
+            "the module name of a style can't be bound, use module=\"name\" instead"
+        );
+        assert_eq!(err.location.offset(), 1);
+    }
+
+    #[test]
+    fn component_name_from_id() {
+        let cases = vec![
+            ("/src/components/user-card.vue", Some("UserCard")),
+            ("/src/App.vue", Some("App")),
+            ("C:\\src\\my_button.vue?vue&type=style", Some("MyButton")),
+            ("/src/user.card.vue", Some("UserCard")),
+            ("/src/404.vue", None),
+            ("/src/.vue", None),
+        ];
+
+        for (id, expected) in cases {
+            assert_eq!(
+                utils::component_name_from_id(id).as_deref(),
+                expected,
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn scope_id() {
+        let id = utils::scope_id(
+            "/home/a/project/src/App.vue",
+            "",
+            Some("/home/a/project"),
+            false,
+        );
+        // The id is the full 64 bit hash of the path relative to the root
+        assert_eq!(id, "3e5cb5544f68999b");
+        assert_eq!(id, utils::hash("src/App.vue"));
+
+        // The same file in another location and with a trailing slash or windows separators in the root
+        assert_eq!(
+            utils::scope_id("/ci/build/src/App.vue", "", Some("/ci/build/"), false),
+            id
+        );
+        assert_eq!(
+            utils::scope_id("C:\\project\\src\\App.vue", "", Some("C:\\project"), false),
+            id
+        );
+
+        // Files outside of the root use the full path
+        assert_eq!(
+            utils::scope_id("/other/src/App.vue", "", Some("/home/a/project"), false),
+            utils::hash("/other/src/App.vue")
+        );
+        assert_eq!(
+            utils::scope_id(
+                "/home/a/projectx/App.vue",
+                "",
+                Some("/home/a/project"),
+                false
+            ),
+            utils::hash("/home/a/projectx/App.vue")
+        );
+
+        // In production the content is part of the hash
+        let a = utils::scope_id("/p/App.vue", "<template>a</template>", Some("/p"), true);
+        let b = utils::scope_id("/p/App.vue", "<template>b</template>", Some("/p"), true);
+        assert_eq!(a, "2b476b14899a3626");
+        assert_eq!(a, utils::hash("App.vue\n<template>a</template>"));
+        assert_ne!(a, b);
+        assert_ne!(a, id);
+
+        assert_ne!(utils::hash("src/A.vue"), utils::hash("src/B.vue"));
+    }
+
+    #[test]
+    fn error_code_frame() {
+        let err = Parser::new_and_parse(
+            "<template>\n  <div>\n    <p>{{ foo + }}</p>\n  </div>\n</template>\n<script>\n</script>",
+            "example",
+        )
+        .unwrap_err();
+
+        assert_eq!(err.line_column(), (3, 16));
+        assert_eq!(err.location.len(), 1);
+        assert_eq!(
+            err.code_frame(),
+            concat!(
+                "1 | <template>\n",
+                "2 |   <div>\n",
+                "3 |     <p>{{ foo + }}</p>\n",
+                "  |                 ^\n",
+                "4 |   </div>\n",
+                "5 | </template>",
+            )
+        );
+    }
+
     #[test]
     fn parse_template_content() {
         let result = Parser::new_and_parse(
@@ -345,6 +566,274 @@ mod tests {
             );
         }
 
+        #[test]
+        fn custom_delimiters() {
+            let cases = vec![("${", "}"), ("[[", "]]"), ("{%", "%}")];
+            for (open, close) in cases {
+                let options = CompilerOptions {
+                    delimiters: (open.to_string(), close.to_string()),
+                    ..CompilerOptions::default()
+                };
+                let parser_input = format!(
+                    "<template><h1>{{{{ foo }}}} {} list[0] + bar {}</h1></template>",
+                    open, close
+                );
+                let result =
+                    Parser::new_and_parse_with_options(&parser_input, "example", options).unwrap();
+                let template = result.template.as_ref().unwrap();
+
+                let mut resp: Vec<char> = Vec::new();
+                children_to_js(&template.content, &result, &mut resp, false);
+                assert_eq!(
+                    resp.iter().collect::<String>(),
+                    "_c('h1',[_vm._v(\"{{ foo }} \"+_vm._s( _vm.list[0] + _vm.bar ))])"
+                );
+            }
+        }
+
+        #[test]
+        fn custom_delimiters_characters_within_expression() {
+            let cases = vec![
+                (("{%", "%}"), "{% a % b %}", "_vm._s( _vm.a % _vm.b )"),
+                (("${", "}"), "${ {a: 1}.a }", "_vm._s( {a: 1}.a )"),
+                (
+                    ("[[", "]]"),
+                    "[[ list[0] + '[[]]' ]]",
+                    "_vm._s( _vm.list[0] + '[[]]' )",
+                ),
+            ];
+            for ((open, close), input, expected) in cases {
+                let options = CompilerOptions {
+                    delimiters: (open.to_string(), close.to_string()),
+                    ..CompilerOptions::default()
+                };
+                let parser_input = format!("<template><p>{}</p></template>", input);
+                let result =
+                    Parser::new_and_parse_with_options(&parser_input, "example", options).unwrap();
+                let template = result.template.as_ref().unwrap();
+
+                let mut resp: Vec<char> = Vec::new();
+                children_to_js(&template.content, &result, &mut resp, false);
+                assert_eq!(
+                    resp.iter().collect::<String>(),
+                    format!("_c('p',[_vm._v({})])", expected)
+                );
+            }
+        }
+
+        #[test]
+        fn text_with_special_characters() {
+            template_to_js_eq(
+                "<p>if a < b && c > d { e } <3 <</p>",
+                "_c('p',[_vm._v(\"if a < b && c > d { e } <3 <\")])",
+            );
+        }
+
+        #[test]
+        fn raw_text_elements() {
+            template_to_js_eq(
+                "<div><script type=\"application/ld+json\">{\"a\": \"<b>\", \"c\": {{ c }}}</script><textarea>a < b <div> {{ d }}</textarea></div>",
+                "_c('div',[_c('script',{attrs:{\"type\":\"application/ld+json\"}},[_vm._v(\"{\\\"a\\\": \\\"<b>\\\", \\\"c\\\": \"+_vm._s( _vm.c )+\"}\")]),_c('textarea',[_vm._v(\"a < b <div> \"+_vm._s( _vm.d ))])])",
+            );
+            template_to_js_eq(
+                "<textarea>a</textareax>b</textarea >",
+                "_c('textarea',[_vm._v(\"a</textareax>b\")])",
+            );
+        }
+
+        #[test]
+        fn comments() {
+            let cases = vec![
+                (
+                    "<template><!-- root --><div><!-- a \"b\" --><p v-if=\"x\" /><!-- c --><p v-else />d<!-- e --></div></template>",
+                    "_c('div',[_vm._e(\" a \\\"b\\\" \"),_vm.x?_c('p'):_c('p'),_vm._v(\"d\"),_vm._e(\" e \")])",
+                ),
+                (
+                    "<template lang=\"pug\">\n//- root\ndiv\n  // a\n  //- b\n  p(v-if=\"x\")\n  // c\n  p(v-else)\n</template>",
+                    "_c('div',[_vm._e(\" a\"),_vm.x?_c('p'):_c('p')])",
+                ),
+            ];
+            for (parser_input, expected) in cases {
+                let options = CompilerOptions {
+                    comments: true,
+                    ..CompilerOptions::default()
+                };
+                let result =
+                    Parser::new_and_parse_with_options(parser_input, "example", options).unwrap();
+                let template = result.template.as_ref().unwrap();
+
+                let mut resp: Vec<char> = Vec::new();
+                children_to_js(&template.content, &result, &mut resp, false);
+                assert_eq!(resp.iter().collect::<String>(), expected);
+            }
+
+            // Comments are dropped by default
+            template_to_js_eq("<div><!-- a --><p /></div>", "_c('div',[_c('p')])");
+        }
+
+        #[test]
+        fn allowed_globals() {
+            template_to_js_eq(
+                "<p :title=\"JSON.stringify(o)\" @click=\"emit(arguments, $event)\">{{ Math.round(x) + parseInt(v) + Date.now() }}, {{ undefined }}</p>",
+                "_c('p',{attrs:{\"title\":JSON.stringify(_vm.o)},on:{\"click\":function($event){_vm.emit(arguments, $event)}}},[_vm._v(_vm._s( Math.round(_vm.x) + parseInt(_vm.v) + Date.now() )+\", \"+_vm._s( undefined ))])",
+            );
+
+            // $event is only defined within v-on handlers
+            template_to_js_eq(
+                "<p>{{ $event }}</p>",
+                "_c('p',[_vm._v(_vm._s( _vm.$event ))])",
+            );
+
+            let options = CompilerOptions {
+                allowed_globals: vec![String::from("Symbol")],
+                ..CompilerOptions::default()
+            };
+            let result = Parser::new_and_parse_with_options(
+                "<template><p>{{ Symbol.iterator in Foo }}</p></template>",
+                "example",
+                options,
+            )
+            .unwrap();
+            let template = result.template.as_ref().unwrap();
+
+            let mut resp: Vec<char> = Vec::new();
+            children_to_js(&template.content, &result, &mut resp, false);
+            assert_eq!(
+                resp.iter().collect::<String>(),
+                "_c('p',[_vm._v(_vm._s( Symbol.iterator in _vm.Foo ))])"
+            );
+        }
+
+        fn pug_template_to_js(pug: &str) -> String {
+            let parser_input = format!("<template lang=\"pug\">{}</template>", pug);
+            let result = Parser::new_and_parse(&parser_input, "example").unwrap();
+            let template = result.template.as_ref().unwrap();
+
+            let mut resp: Vec<char> = Vec::new();
+            children_to_js(&template.content, &result, &mut resp, false);
+            resp.iter().collect()
+        }
+
+        #[test]
+        fn pug_template() {
+            let result = pug_template_to_js(
+                "
+div.card#main(:title=\"title\")
+  h1.title Hello {{ name }}!
+  p
+    | first line
+    | second {{ other }}
+  ul
+    li(v-for=\"item in items\", class=\"item\") {{ item }}
+  input(type=\"text\" disabled)/
+  .footer: a(href=\"/\") Home
+  //- a comment
+    that spans multiple lines
+  p(v-if=\"show\").
+    Some block
+    text
+  p(v-else) Nothing
+",
+            );
+            assert_eq!(
+                result,
+                concat!(
+                    "_c('div',{staticClass:\"card\",attrs:{\"id\":\"main\",\"title\":_vm.title}},[",
+                    "_c('h1',{staticClass:\"title\"},[_vm._v(\"Hello \"+_vm._s( _vm.name )+\"!\")]),",
+                    "_c('p',[_vm._v(\"first line\"+\" \"+\"second \"+_vm._s( _vm.other ))]),",
+                    "_c('ul',_vm._l((_vm.items),(item)=>_c('li',{staticClass:\"item\"},[_vm._v(_vm._s( item ))])),0),",
+                    "_c('input',{attrs:{\"type\":\"text\",\"disabled\":true}}),",
+                    "_c('div',{staticClass:\"footer\"},[_c('a',{attrs:{\"href\":\"/\"}},[_vm._v(\"Home\")])]),",
+                    "_vm.show?_c('p',[_vm._v(\"Some block\"+\" \"+\"text\")]):_c('p',[_vm._v(\"Nothing\")])",
+                    "])",
+                ),
+            );
+        }
+
+        #[test]
+        fn pug_unquoted_attribute_values() {
+            assert_eq!(
+                pug_template_to_js("a(href=url, title=item.name + '!' data-x=fn(a, b))/"),
+                "_c('a',{attrs:{\"href\":_vm.url,\"title\":_vm.item.name + '!',\"data-x\":_vm.fn(_vm.a, _vm.b)}})",
+            );
+            assert_eq!(
+                pug_template_to_js("input(value=1 disabled=false)/"),
+                "_c('input',{attrs:{\"value\":1,\"disabled\":false}})",
+            );
+        }
+
+        #[test]
+        fn root_validation() {
+            let render = |html: &str| {
+                let parser_input = format!("<template>{}</template>", html);
+                let result = Parser::new_and_parse(&parser_input, "example").unwrap();
+                let mut resp: Vec<char> = Vec::new();
+                template::to_js::template_to_js(&result, &mut resp)
+                    .map(|_| resp.iter().collect::<String>())
+            };
+
+            let valid = vec![
+                ("<div />", "_c('div')"),
+                (
+                    "<div v-if=\"a\" /> <p v-else-if=\"b\" /> <span v-else />",
+                    "_vm.a?_c('div'):_vm.b?_c('p'):_c('span')",
+                ),
+                ("<!-- comment --><div />", "_c('div')"),
+            ];
+            for (html, expected_render) in valid {
+                let result = render(html).unwrap();
+                assert!(
+                    result.ends_with(&format!("return {}\n}};", expected_render)),
+                    "{}",
+                    result
+                );
+            }
+
+            let multiple_roots_err = "component template should contain exactly one root element, use v-if, v-else-if and v-else to conditionally render a root element";
+            let invalid = vec![
+                ("<div /><p />", multiple_roots_err, 18),
+                ("<div v-if=\"a\" /><p v-if=\"b\" />", multiple_roots_err, 27),
+                ("<slot />", "cannot use <slot> as component root element because it may contain multiple nodes", 11),
+                ("<template><div /></template>", "cannot use <template> as component root element because it may contain multiple nodes", 11),
+                ("<div v-for=\"a in b\" />", "cannot use v-for on the component root element because it renders multiple elements", 11),
+                ("<div />text", "text outside the root element is not allowed", 17),
+                ("<div />{{ foo }}", "text outside the root element is not allowed", 19),
+            ];
+            for (html, expected_err, expected_offset) in invalid {
+                let err = render(html).unwrap_err();
+                assert_eq!(err.message, expected_err);
+                assert_eq!(err.location.offset(), expected_offset, "{}", html);
+            }
+        }
+
+        #[test]
+        fn functional_template() {
+            let result = Parser::new_and_parse(
+                "<template functional><h1 :title='props.title' @click='listeners.click'>{{ props.count }} {{ foo }}</h1><span /></template>",
+                "example",
+            )
+            .unwrap();
+            assert!(result.template.as_ref().unwrap().functional);
+
+            let mut resp: Vec<char> = Vec::new();
+            super::super::super::template::to_js::template_to_js(&result, &mut resp).unwrap();
+            assert_eq!(
+                resp.iter().collect::<String>(),
+                concat!(
+                    "\nc._compiled = true;",
+                    "\nc.functional = true;",
+                    "\nc.staticRenderFns = [];",
+                    "\nc.render = function(_h, _vm) {",
+                    "\n    const _c = _vm._c;",
+                    "\n    return [",
+                    "_c('h1',{attrs:{\"title\":_vm.props.title},on:{\"click\":function($event){_vm.listeners.click}}},",
+                    "[_vm._v(_vm._s( _vm.props.count )+_vm._s( _vm.foo ))]),",
+                    "_c('span')",
+                    "]\n};",
+                ),
+            );
+        }
+
         mod args {
             use super::*;
 
@@ -356,6 +845,43 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn svg_and_math_ml_args() {
+                template_to_js_eq(
+                    "<svg viewBox=\"0 0 24 24\"><linearGradient gradientUnits=\"userSpaceOnUse\" /><use xlink:href=\"#icon\" /></svg>",
+                    "_c('svg',{attrs:{\"viewBox\":\"0 0 24 24\"}},[_c('linearGradient',{attrs:{\"gradientUnits\":\"userSpaceOnUse\"}}),_c('use',{attrs:{\"xlink:href\":\"#icon\"}})])",
+                );
+                template_to_js_eq(
+                    "<math><mfrac><mi>a</mi><mn>2</mn></mfrac></math>",
+                    "_c('math',[_c('mfrac',[_c('mi',[_vm._v(\"a\")]),_c('mn',[_vm._v(\"2\")])])])",
+                );
+            }
+
+            #[test]
+            fn unusual_arg_names() {
+                template_to_js_eq(
+                    "<div data-foo.bar=\"x\" aria-label='y' @data=\"z\" v-on:update:value.stop=\"a\" />",
+                    "_c('div',{attrs:{\"data-foo.bar\":\"x\",\"aria-label\":\"y\"},on:{\"data\":function($event){_vm.z},\"update:value\":function($event){_vm.a}}})",
+                );
+            }
+
+            #[test]
+            fn static_and_bound_class_and_style() {
+                template_to_js_eq(
+                    "<div class=\"card\" :class=\"{active: isActive}\" style=\"color: red\" :style=\"styles\" />",
+                    "_c('div',{staticClass:\"card\",class:{active: _vm.isActive},staticStyle:{\"color\":\"red\"},style:_vm.styles})",
+                );
+                // The order of the arguments should not matter
+                template_to_js_eq(
+                    "<div :class=\"classes\" class=\"card\" />",
+                    "_c('div',{staticClass:\"card\",class:_vm.classes})",
+                );
+                template_to_js_eq(
+                    "<div style=\"font-family: &quot;A&quot;; color: red\" />",
+                    "_c('div',{staticStyle:{\"font-family\":\"\\\"A\\\"\",\"color\":\"red\"}})",
+                );
+            }
+
             #[test]
             fn v_bind_arg() {
                 template_to_js_eq(
@@ -383,22 +909,41 @@ mod tests {
             fn v_on_arg() {
                 template_to_js_eq(
                     "<h1 v-on:value='value($event)'>Hmm</h1>",
-                    "_c('h1',{on:{\"value\":$event=>{_vm.value($_vm.event)}}},[_vm._v(\"Hmm\")])",
+                    "_c('h1',{on:{\"value\":function($event){_vm.value($event)}}},[_vm._v(\"Hmm\")])",
                 );
 
                 template_to_js_eq(
                     "<custom-component v-on:value='value($event)'>Hmm</custom-component>",
-                    "_c('custom-component',{on:{\"value\":$event=>{_vm.value($_vm.event)}}},[_vm._v(\"Hmm\")])",
+                    "_c('custom-component',{on:{\"value\":function($event){_vm.value($event)}}},[_vm._v(\"Hmm\")])",
                 );
 
                 template_to_js_eq(
                     "<h1 @value='value($event)'>Hmm</h1>",
-                    "_c('h1',{on:{\"value\":$event=>{_vm.value($_vm.event)}}},[_vm._v(\"Hmm\")])",
+                    "_c('h1',{on:{\"value\":function($event){_vm.value($event)}}},[_vm._v(\"Hmm\")])",
                 );
 
                 template_to_js_eq(
                     "<custom-component @value='value($event)'>Hmm</custom-component>",
-                    "_c('custom-component',{on:{\"value\":$event=>{_vm.value($_vm.event)}}},[_vm._v(\"Hmm\")])",
+                    "_c('custom-component',{on:{\"value\":function($event){_vm.value($event)}}},[_vm._v(\"Hmm\")])",
+                );
+            }
+
+            #[test]
+            fn v_on_function_expression() {
+                // Function expressions are used as the listener instead of being wrapped in a function
+                template_to_js_eq(
+                    "<h1 @click='function (e) { value(e) }'>Hmm</h1>",
+                    "_c('h1',{on:{\"click\":function (e) { _vm.value(e) }}},[_vm._v(\"Hmm\")])",
+                );
+
+                template_to_js_eq(
+                    "<h1 @click='e => value(e)'>Hmm</h1>",
+                    "_c('h1',{on:{\"click\":e => _vm.value(e)}},[_vm._v(\"Hmm\")])",
+                );
+
+                template_to_js_eq(
+                    "<h1 @click='(e) => value(e); other()'>Hmm</h1>",
+                    "_c('h1',{on:{\"click\":function($event){(e) => _vm.value(e); _vm.other()}}},[_vm._v(\"Hmm\")])",
                 );
             }
 
@@ -622,6 +1167,30 @@ mod tests {
                 "v-slot needs a target (for example: v-slot:foo)"
             );
         }
+
+        #[test]
+        fn unsupported_directives() {
+            for (html, expected) in [
+                ("<div v-pre></div>", "v-pre is not supported"),
+                ("<div v-cloak></div>", "v-cloak is not supported"),
+                ("<div v-once></div>", "v-once is not supported"),
+                (
+                    "<div :[key]=\"value\"></div>",
+                    "dynamic argument [key] on v-bind is not supported",
+                ),
+                (
+                    "<div @[ev]=\"handler\"></div>",
+                    "dynamic argument [ev] on v-on is not supported",
+                ),
+                (
+                    "<div v-bind:[a.b].prop=\"value\"></div>",
+                    "dynamic argument [a.b] on v-bind is not supported",
+                ),
+            ] {
+                let err = template_to_js_result(html).unwrap_err();
+                assert_eq!(err.message, expected);
+            }
+        }
     }
 
     mod js_tests {
@@ -629,10 +1198,12 @@ mod tests {
 
         fn parse_js(js: &str, expected_global_vars: Vec<&str>, expected_result: &str) {
             let mut parser = Parser::new(&format!("{}{}", js, "}}"));
-            let global_var_locations = js::parse_template_var(&mut parser).unwrap();
+            let js_location = js::parse_template_var(&mut parser).unwrap();
+            let parsed =
+                js::parse_template_js(&parser, &js_location, js::TemplateJs::Expression).unwrap();
 
             let mut global_var_locations_iter =
-                global_var_locations.iter().map(|e| e.string(&parser));
+                parsed.references.iter().map(|e| e.location.string(&parser));
             let mut expected_global_vars_iter = expected_global_vars.iter().map(|e| e.to_string());
 
             loop {
@@ -645,13 +1216,7 @@ mod tests {
                 }
             }
 
-            let mut global_vars = Vec::new();
-            for location in global_var_locations.iter() {
-                global_vars.push(location.string(&parser));
-            }
-
-            let js_with_vm_references =
-                js::add_vm_references(&parser, &SourceLocation(0, js.len()), &global_var_locations);
+            let js_with_vm_references = js::add_vm_references(&parser, &js_location, &parsed);
 
             assert_eq!(js_with_vm_references, expected_result);
         }
@@ -672,22 +1237,18 @@ mod tests {
             parse_js("count <<= 1", vec!["count"], "_vm.count <<= 1");
 
             parse_js("foo.bar.baz = 1", vec!["foo"], "_vm.foo.bar.baz = 1");
-            parse_js("foo?.bar?.baz = 1", vec!["foo"], "_vm.foo?.bar?.baz = 1");
+            parse_js("foo?.bar?.baz", vec!["foo"], "_vm.foo?.bar?.baz");
             parse_js("foo['bar'].baz = 1", vec!["foo"], "_vm.foo['bar'].baz = 1");
-            parse_js(
-                "foo?.['bar']?.baz = 1",
-                vec!["foo"],
-                "_vm.foo?.['bar']?.baz = 1",
-            );
+            parse_js("foo?.['bar']?.baz", vec!["foo"], "_vm.foo?.['bar']?.baz");
             parse_js(
                 "foo['bar']['baz'] = 1",
                 vec!["foo"],
                 "_vm.foo['bar']['baz'] = 1",
             );
             parse_js(
-                "foo?.['bar']?.['baz'] = 1",
+                "foo?.['bar']?.['baz']",
                 vec!["foo"],
-                "_vm.foo?.['bar']?.['baz'] = 1",
+                "_vm.foo?.['bar']?.['baz']",
             );
 
             parse_js(
@@ -696,9 +1257,9 @@ mod tests {
                 "_vm.foo[_vm.bar][_vm.baz] = 1",
             );
             parse_js(
-                "foo?.[bar]?.[baz] = 1",
+                "foo?.[bar]?.[baz]",
                 vec!["foo", "bar", "baz"],
-                "_vm.foo?.[_vm.bar]?.[_vm.baz] = 1",
+                "_vm.foo?.[_vm.bar]?.[_vm.baz]",
             );
         }
 
@@ -727,6 +1288,308 @@ mod tests {
                 "_vm.foo && _vm.bar",
             );
         }
+        #[test]
+        fn scopes() {
+            parse_js(
+                "xs.filter(x => x.ok)",
+                vec!["xs"],
+                "_vm.xs.filter(x => x.ok)",
+            );
+            parse_js(
+                "xs.map(({ id, value = fallback }, i) => id + i + y)",
+                vec!["xs", "fallback", "y"],
+                "_vm.xs.map(({ id, value = _vm.fallback }, i) => id + i + _vm.y)",
+            );
+            parse_js(
+                "async (a, ...b) => await a(b, c)",
+                vec!["c"],
+                "async (a, ...b) => await a(b, _vm.c)",
+            );
+            parse_js("async(a)", vec!["async", "a"], "_vm.async(_vm.a)");
+            parse_js("{ a, b: c }", vec!["a", "c"], "{ a:_vm.a, b: _vm.c }");
+            parse_js("[{ a }] = b", vec!["a", "b"], "[{ a:_vm.a }] = _vm.b");
+            parse_js(
+                "/a[/]b/g.test(text) / 2",
+                vec!["text"],
+                "/a[/]b/g.test(_vm.text) / 2",
+            );
+            parse_js("`${a} b ${c}`", vec!["a", "c"], "`${_vm.a} b ${_vm.c}`");
+            parse_js("foo.bar.baz", vec!["foo"], "_vm.foo.bar.baz");
+            parse_js(
+                "function (a) { return this.b + a + arguments[0] + c }",
+                vec!["c"],
+                "function (a) { return this.b + a + arguments[0] + _vm.c }",
+            );
+            parse_js(
+                "function f() { g(); function g() { new.target } var v = f }",
+                vec![],
+                "function f() { g(); function g() { new.target } var v = f }",
+            );
+            parse_js("() => this.a", vec!["this"], "() => _vm.a");
+            parse_js(
+                "class A { b = this.c; d() { return A } } + e",
+                vec!["e"],
+                "class A { b = this.c; d() { return A } } + _vm.e",
+            );
+
+            let parser =
+                Parser::new("outer: for (const a of b) { if (a) continue outer; let c = a } c");
+            let location = SourceLocation(0, parser.source_chars_len);
+            let parsed =
+                js::parse_template_js(&parser, &location, js::TemplateJs::Statements).unwrap();
+            assert_eq!(
+                js::add_vm_references(&parser, &location, &parsed),
+                "outer: for (const a of _vm.b) { if (a) continue outer; let c = a } _vm.c"
+            );
+        }
+
+        #[test]
+        fn lower_optional_chaining() {
+            let cases =
+                vec![
+                ("foo?.bar", "((_t=_vm.foo)==null?void 0:_t.bar)"),
+                ("foo?.[bar].baz", "((_t=_vm.foo)==null?void 0:_t[_vm.bar].baz)"),
+                (
+                    "foo?.bar?.baz",
+                    "((_t=((_t=_vm.foo)==null?void 0:_t.bar))==null?void 0:_t.baz)",
+                ),
+                ("foo?.(a, b)", "((_t=_vm.foo)==null?void 0:_t(_vm.a, _vm.b))"),
+                (
+                    "foo.bar?.()",
+                    "((_t=(_o=_vm.foo).bar)==null?void 0:_t.call(_o))",
+                ),
+                (
+                    "foo?.bar?.(a)",
+                    "((_t=((_t=(_o=_vm.foo))==null?void 0:_t.bar))==null?void 0:_t.call(_o,_vm.a))",
+                ),
+                (
+                    "foo?.bar.baz?.()",
+                    "((_t=((_t=_vm.foo)==null?void 0:(_o=_t.bar).baz))==null?void 0:_t.call(_o))",
+                ),
+                ("foo ?? bar", "((_t=_vm.foo)!=null?_t:_vm.bar)"),
+                (
+                    "a ?? b ?? c",
+                    "((_t=((_t=_vm.a)!=null?_t:_vm.b))!=null?_t:_vm.c)",
+                ),
+                (
+                    "a?.b ?? 'default' + c",
+                    "((_t=((_t=_vm.a)==null?void 0:_t.b))!=null?_t:'default' + _vm.c)",
+                ),
+                ("(a?.b).c", "(((_t=_vm.a)==null?void 0:_t.b)).c"),
+                ("delete a?.b", "((_t=_vm.a)==null?true:delete _t.b)"),
+                (
+                    "delete a?.b?.[c]",
+                    "((_t=((_t=_vm.a)==null?void 0:_t.b))==null?true:delete _t[_vm.c])",
+                ),
+                ("!delete a?.b.c", "!((_t=_vm.a)==null?true:delete _t.b.c)"),
+                (
+                    "items.map(item => item?.name)",
+                    "_vm.items.map(item => ((_t=item)==null?void 0:_t.name))",
+                ),
+            ];
+
+            for (js, expected) in cases {
+                let mut parser = Parser::new(js);
+                parser.options.lower_optional_chaining = true;
+                let location = SourceLocation(0, parser.source_chars_len);
+                let parsed =
+                    js::parse_template_js(&parser, &location, js::TemplateJs::Expression).unwrap();
+                assert_eq!(js::add_vm_references(&parser, &location, &parsed), expected);
+            }
+
+            // The temporary variables are declared in the render function
+            let options = CompilerOptions {
+                lower_optional_chaining: true,
+                ..CompilerOptions::default()
+            };
+            let result = Parser::new_and_parse_with_options(
+                "<template><p>{{ a?.b }}</p></template>",
+                "example",
+                options,
+            )
+            .unwrap();
+            let mut resp: Vec<char> = Vec::new();
+            template::to_js::template_to_js(&result, &mut resp).unwrap();
+            assert!(resp.iter().collect::<String>().ends_with(
+                "\n    var _t,_o;\n    return _c('p',[_vm._v(_vm._s( ((_t=_vm.a)==null?void 0:_t.b) ))])\n};"
+            ));
+        }
+
+        fn validate(js: &str, kind: js::TemplateJs) -> Result<(), ParserError> {
+            let parser = Parser::new(js);
+            js::parse_template_js(&parser, &SourceLocation(0, parser.source_chars_len), kind)
+                .map(|_| ())
+        }
+
+        #[test]
+        fn valid_expressions() {
+            let expressions = vec![
+                "a + b * c ** -d",
+                "a ? b : c ? d : e",
+                "a?.b?.[c]?.(d)",
+                "(a ?? b) || c",
+                "a ?? (b && c) ?? d",
+                "typeof a === 'string' && !(b instanceof C)",
+                "{ a, b: [c, ...d], [e]: f, 'g': 1, h() { return 1 } }",
+                "`a ${b + `c ${d}`} e`",
+                "items.filter(item => item.active).map(({ id }, index) => id + index)",
+                "async (a, b = 1, ...c) => { await a; return b }",
+                "function (a) { return a } ",
+                "/[/]+/g.test(a) ? a / 2 : a",
+                "new Date().getTime() + new.target",
+                "msg | capitalize",
+                "[a, b] = [b, a]",
+                "({ a = 1, b: { c } } = d)",
+                "0x1F + 1_000 + .5e10 + 10n",
+                "class extends A { #b = 1; static { this.c = 2 } get d() { return this.#b } }",
+            ];
+            for expression in expressions {
+                validate(expression, js::TemplateJs::Expression)
+                    .unwrap_or_else(|err| panic!("{}: {}", expression, err.message));
+            }
+
+            let statements = vec![
+                "",
+                "a = 1; b()",
+                "a = 1\nb()",
+                "if (a) { b() } else c = 1",
+                "for (const item of items) total += item",
+                "for (let i = 0; i < 10; i++) {}",
+                "try { a() } catch { b() } finally { c() }",
+                "const { a, b: [c] } = $event",
+                "function (e) { a(e) }",
+                "function named(e) { a(e) }; b()",
+                "async e => await a(e)",
+                "(a, b) => a + b",
+                "(a = 1); b()",
+            ];
+            for statement in statements {
+                validate(statement, js::TemplateJs::Statements)
+                    .unwrap_or_else(|err| panic!("{}: {}", statement, err.message));
+            }
+
+            for target in ["a", "a.b", "a[b].c", "(a)"] {
+                validate(target, js::TemplateJs::AssignmentTarget)
+                    .unwrap_or_else(|err| panic!("{}: {}", target, err.message));
+            }
+
+            for params in ["", "a", "a, b = 1", "{ item, index }", "[a, b], ...c"] {
+                validate(params, js::TemplateJs::Params)
+                    .unwrap_or_else(|err| panic!("{}: {}", params, err.message));
+            }
+        }
+
+        #[test]
+        fn invalid_expressions() {
+            let invalid = vec![
+                ("", js::TemplateJs::Expression, "expected an expression", 0),
+                (
+                    "a +",
+                    js::TemplateJs::Expression,
+                    "unexpected the end of the expression",
+                    3,
+                ),
+                ("a b", js::TemplateJs::Expression, "unexpected 'b'", 2),
+                ("a; b", js::TemplateJs::Expression, "unexpected ';'", 1),
+                (
+                    "(a, b",
+                    js::TemplateJs::Expression,
+                    "expected ',' but got the end of the expression",
+                    5,
+                ),
+                ("'abc", js::TemplateJs::Expression, "unterminated string", 0),
+                (
+                    "1 = a",
+                    js::TemplateJs::Expression,
+                    "invalid assignment target",
+                    0,
+                ),
+                (
+                    "a?.b = 1",
+                    js::TemplateJs::Expression,
+                    "invalid assignment target",
+                    0,
+                ),
+                (
+                    "{ a = 1 }",
+                    js::TemplateJs::Expression,
+                    "invalid shorthand property initializer",
+                    4,
+                ),
+                (
+                    "(a + 1) => a",
+                    js::TemplateJs::Expression,
+                    "invalid arrow function parameters",
+                    0,
+                ),
+                (
+                    "a || b ?? c",
+                    js::TemplateJs::Expression,
+                    "?? can not be mixed with || or && without parentheses",
+                    7,
+                ),
+                (
+                    "a ?? b && c",
+                    js::TemplateJs::Expression,
+                    "?? can not be mixed with || or && without parentheses",
+                    2,
+                ),
+                (
+                    "if (a) b(",
+                    js::TemplateJs::Statements,
+                    "unexpected the end of the expression",
+                    9,
+                ),
+                (
+                    "a + 1",
+                    js::TemplateJs::AssignmentTarget,
+                    "expected a variable or property to assign to",
+                    0,
+                ),
+                (
+                    "a + 1",
+                    js::TemplateJs::Params,
+                    "expected ',' but got '+'",
+                    2,
+                ),
+            ];
+            for (js, kind, expected_err, expected_offset) in invalid {
+                let err = validate(js, kind).unwrap_err();
+                assert_eq!(err.message, expected_err, "{}", js);
+                assert_eq!(err.location.offset(), expected_offset, "{}", js);
+            }
+        }
+
+        #[test]
+        fn invalid_template_expressions() {
+            let invalid = vec![
+                (
+                    "<div :title=\"a +\" />",
+                    "unexpected the end of the expression",
+                    26,
+                ),
+                ("<div>{{ a b }}</div>", "unexpected 'b'", 20),
+                (
+                    "<input v-model=\"a()\" />",
+                    "expected a variable or property to assign to",
+                    26,
+                ),
+                (
+                    "<div v-for=\"item in items.\" />",
+                    "expected a property name but got the end of the expression",
+                    36,
+                ),
+            ];
+            for (html, expected_err, expected_offset) in invalid {
+                let parser_input = format!("<template>{}</template>", html);
+                let err = match Parser::new_and_parse(&parser_input, "example") {
+                    Ok(_) => panic!("expected {} to fail", html),
+                    Err(err) => err,
+                };
+                assert_eq!(err.message, expected_err, "{}", html);
+                assert_eq!(err.location.offset(), expected_offset, "{}", html);
+            }
+        }
     }
 
     mod style_tests {
@@ -926,5 +1789,511 @@ mod tests {
             parse_style("foo::v-deep bar {}", "foo[data-v-example] bar {}");
             parse_style("foo::v-deep bar baz {}", "foo[data-v-example] bar baz {}");
         }
+
+        #[test]
+        fn inline_style() {
+            let cases = vec![
+                ("", vec![]),
+                ("color: red", vec![("color", "red")]),
+                (
+                    "color:red;font-size: 12px ;",
+                    vec![("color", "red"), ("font-size", "12px")],
+                ),
+                (
+                    "color: red !important; width: 1px",
+                    vec![("color", "red !important"), ("width", "1px")],
+                ),
+                (
+                    "font-family: 'a;b', \"c:d\"",
+                    vec![("font-family", "'a;b', \"c:d\"")],
+                ),
+                (
+                    "background: url(data:image/png;base64,abc); color: red",
+                    vec![
+                        ("background", "url(data:image/png;base64,abc)"),
+                        ("color", "red"),
+                    ],
+                ),
+                (
+                    "font-family: &quot;A;B&quot;, &#39;C&#39;; content: &amp;x&; color: red",
+                    vec![
+                        ("font-family", "\"A;B\", 'C'"),
+                        ("content", "&x&"),
+                        ("color", "red"),
+                    ],
+                ),
+            ];
+
+            for (style, expected) in cases {
+                let expected: Vec<(String, String)> = expected
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect();
+                assert_eq!(style::parse_inline_style(style), expected);
+            }
+        }
+
+        #[test]
+        fn v_bind() {
+            let cases =
+                vec![
+                ("", ""),
+                ("a { color: v-bind(color) }", "a { color: var(--example-color) }"),
+                (
+                    "a { color: v-bind( 'theme.color' ); width: v-bind(\"size + 'px'\") }",
+                    "a { color: var(--example-theme_color-3bfacd4858e60f5b); width: var(--example-size____px_-5f708429ce38adbd) }",
+                ),
+                (
+                    "a { width: v-bind(sizes(1)) }",
+                    "a { width: var(--example-sizes_1_-8de09c364fe69c09) }",
+                ),
+                // Comments, strings and other functions are left as is
+                (
+                    "/* v-bind(a) */ a { content: 'v-bind(b)'; --x: my-v-bind(c) }",
+                    "/* v-bind(a) */ a { content: 'v-bind(b)'; --x: my-v-bind(c) }",
+                ),
+                ("a { color: v-bind() }", "a { color: v-bind() }"),
+            ];
+
+            for (css, expected) in cases {
+                assert_eq!(style::rewrite_v_binds(css, "example"), expected);
+            }
+
+            // Different expressions never share a css var
+            assert_ne!(
+                style::css_var_name("example", "theme.bg"),
+                style::css_var_name("example", "theme_bg"),
+            );
+        }
+
+        #[test]
+        fn v_bind_component() {
+            let result = Parser::new_and_parse(
+                "<template><div /></template>
+                <style>a { color: v-bind(color); background: v-bind('theme.bg') }</style>
+                <style scoped>a { color: v-bind(color) }</style>",
+                "example",
+            )
+            .unwrap();
+
+            let css_vars: Vec<String> = result
+                .css_vars
+                .iter()
+                .map(|css_var| css_var.string(&result))
+                .collect();
+            assert_eq!(css_vars, vec!["color", "theme.bg"]);
+
+            let mut resp: Vec<char> = Vec::new();
+            template::to_js::css_vars_to_js(&result, "example", &mut resp).unwrap();
+            let resp: String = resp.iter().collect();
+            assert!(resp.contains(concat!(
+                "\n    return {",
+                "\n        \"--example-color\": (_vm.color),",
+                "\n        \"--example-theme_bg-85b6801244f3aa1f\": (_vm.theme.bg),",
+                "\n    };",
+            )));
+            assert!(resp.contains("c.mounted = [function() {"));
+
+            // Errors within the expressions point to the expression within the style
+            let result = Parser::new_and_parse(
+                "<template><div /></template><style>a { color: v-bind('a +') }</style>",
+                "example",
+            )
+            .unwrap();
+            let err =
+                template::to_js::css_vars_to_js(&result, "example", &mut Vec::new()).unwrap_err();
+            assert_eq!(err.message, "unexpected the end of the expression");
+            assert_eq!(err.location.offset(), 57);
+
+            let err = Parser::new_and_parse(
+                "<template functional><div /></template><style>a { color: v-bind(color) }</style>",
+                "example",
+            )
+            .unwrap_err();
+            assert_eq!(
+                err.message,
+                "v-bind() within styles can't be used in combination with a functional template"
+            );
+            assert_eq!(err.location.offset(), 64);
+        }
+    }
+
+    mod script_setup_tests {
+        use super::super::super::template::to_js::template_to_js;
+        use super::*;
+
+        fn compile_setup(input: &str) -> String {
+            let result = Parser::new_and_parse(input, "example").unwrap();
+            js::compile_script_setup(&result, result.script_setup.as_ref().unwrap()).unwrap()
+        }
+
+        fn render(input: &str) -> String {
+            let result = Parser::new_and_parse(input, "example").unwrap();
+            let mut resp: Vec<char> = Vec::new();
+            template_to_js(&result, &mut resp).unwrap();
+            resp.iter().collect()
+        }
+
+        #[test]
+        fn bindings() {
+            let result = Parser::new_and_parse(
+                "<script setup lang=\"ts\">
+                import Foo, { ref, computed as c, type Bar } from 'vue'
+                import type { Baz } from './types'
+                import * as utils from './utils'
+                import './side-effect.css'
+                const a = 1, { b, c: [d, ...e] = [] } = obj
+                let f: Map<string, number> = new Map<string, number>(), g
+                const h = () => {
+                    const not_top_level = 1
+                }
+                function i() {}
+                async function j() {}
+                class K {}
+                const l = `${m}`
+                interface Props { n: string }
+                </script>",
+                "example",
+            )
+            .unwrap();
+
+            let info = &result.script_setup.as_ref().unwrap().info;
+            let imports: Vec<Vec<String>> = info
+                .imports
+                .iter()
+                .map(|import| import.bindings.clone())
+                .collect();
+            assert_eq!(
+                imports,
+                vec![vec!["Foo", "ref", "c"], vec![], vec!["utils"], vec![],]
+            );
+            assert_eq!(
+                info.bindings,
+                vec!["a", "b", "d", "e", "f", "g", "h", "i", "j", "K", "l"]
+            );
+        }
+
+        #[test]
+        fn compile() {
+            let output = compile_setup(
+                "<script setup>
+import { ref } from 'vue'
+const props = defineProps({ title: String })
+const emit = defineEmits(['change'])
+const count = ref(0)
+defineExpose({ count })
+</script>",
+            );
+
+            assert_eq!(
+                output,
+                "import { ref } from 'vue'
+export default {
+props: { title: String },
+emits: ['change'],
+setup(__props, { expose: __expose, emit: __emit }) {
+
+const props = __props
+const emit = __emit
+const count = ref(0)
+__expose({ count })
+
+return { ref, props, emit, count }
+}
+};
+"
+            );
+        }
+
+        #[test]
+        fn compile_with_normal_script() {
+            let output = compile_setup(
+                "<script>
+export default { name: 'Foo' }
+</script>
+<script setup>
+const a = 1
+</script>",
+            );
+
+            assert_eq!(
+                output,
+                "
+const __default__ = { name: 'Foo' }
+
+export default Object.assign(__default__, {
+setup(__props, { expose: __expose, emit: __emit }) {
+const a = 1
+
+return { a }
+}
+});
+"
+            );
+        }
+
+        #[test]
+        fn compile_inline() {
+            let compile_inline = |input: &str| -> Option<String> {
+                let result = Parser::new_and_parse(input, "example").unwrap();
+                js::compile_inline_script(&result, |name| name == "c").unwrap()
+            };
+
+            assert_eq!(
+                compile_inline("<script>import a from 'a'\nexport default { a }</script>").unwrap(),
+                "import a from 'a'\nconst __default__ = { a }\nconst c = __default__ || {};",
+            );
+            assert_eq!(
+                compile_inline("<script>export const a = 1</script>").unwrap(),
+                "export const a = 1\nconst __default__ = {};\nconst c = __default__ || {};",
+            );
+            assert_eq!(
+                compile_inline("<script setup>\nconst a = 1\n</script>").unwrap(),
+                "const c = {\nsetup(__props, { expose: __expose, emit: __emit }) {\nconst a = 1\n\nreturn { a }\n}\n};\n",
+            );
+
+            // These need to be imported from a separate module
+            let not_inlined = vec![
+                "<template><div /></template>",
+                "<script lang=\"ts\">export default {}</script>",
+                "<script src=\"./script.js\"></script>",
+                "<script>const a = {}\nexport { a as default }</script>",
+                "<script>import c from 'c'\nexport default {}</script>",
+                "<script>export function c() {}\nexport default {}</script>",
+                "<script>export default {}</script><script setup>import { c } from 'c'</script>",
+            ];
+            for input in not_inlined {
+                assert_eq!(compile_inline(input), None, "{}", input);
+            }
+
+            // Names declared within the setup function don't conflict with the main module
+            assert!(compile_inline("<script setup>\nconst c = 1\n</script>").is_some());
+        }
+
+        #[test]
+        fn with_defaults() {
+            let output = compile_setup(
+                "<script setup lang=\"ts\">
+const props = withDefaults(defineProps<{ size?: string; items?: string[]; label: string }>(), {
+    size: 'small',
+    items() { return [] },
+    'label': label,
+})
+</script>",
+            );
+
+            assert_eq!(
+                output,
+                "export default {
+props: { size: { type: String, required: false, default: 'small' }, items: { type: Array, required: false, default() { return [] } }, label: { type: String, required: true, default: label } },
+setup(__props, { expose: __expose, emit: __emit }) {
+const props = __props
+
+return { props }
+}
+};
+"
+            );
+        }
+
+        #[test]
+        fn await_within_functions() {
+            let output = compile_setup(
+                "<script setup>
+async function load() { await fetch('a') }
+const run = async () => await load()
+const obj = { async load() { await load() }, await: 1 }
+</script>",
+            );
+            assert!(output.contains("const run = async () => await load()"));
+        }
+
+        fn compile_props_and_emits(input: &str) -> String {
+            let output = compile_setup(input);
+            let export_start = output.find("export default ").unwrap();
+            let start = export_start + output[export_start..].find("{\n").unwrap() + 2;
+            let end = output.find("setup(__props").unwrap();
+            output[start..end].to_string()
+        }
+
+        #[test]
+        fn type_based_props() {
+            let cases = vec![
+                (
+                    "const props = defineProps<{ title: string; count?: number }>()",
+                    "props: { title: { type: String, required: true }, count: { type: Number, required: false } },\n",
+                ),
+                (
+                    "defineProps<{
+                        a: string | number
+                        b?: 'small' | 'large'
+                        c: boolean
+                        d: string[]
+                        e: Array<Map<string, number>>
+                        f: { nested: string }
+                        g: (value: string) => void
+                        h(): void
+                        i: Date
+                        j: any
+                        k: Foo
+                        'data-l': string | null
+                        m?: string | undefined
+                    }>()",
+                    "props: { a: { type: [String, Number], required: true }, b: { type: String, required: false }, c: { type: Boolean, required: true }, d: { type: Array, required: true }, e: { type: Array, required: true }, f: { type: Object, required: true }, g: { type: Function, required: true }, h: { type: Function, required: true }, i: { type: Date, required: true }, j: { type: null, required: true }, k: { type: null, required: true }, 'data-l': { type: [String, null], required: true }, m: { type: String, required: false } },\n",
+                ),
+                (
+                    "interface Base { id: number }
+                    interface Props extends Base { title: string; size: Size; user: User }
+                    interface User { name: string }
+                    type Size = 'small' | 'large'
+                    defineProps<Props>()",
+                    "props: { id: { type: Number, required: true }, title: { type: String, required: true }, size: { type: String, required: true }, user: { type: Object, required: true } },\n",
+                ),
+                (
+                    "type Props = { a: string } & { b?: number }
+                    defineProps<Props>()",
+                    "props: { a: { type: String, required: true }, b: { type: Number, required: false } },\n",
+                ),
+                (
+                    "defineEmits<{ (e: 'change', value: number): void; (e: 'update' | 'delete'): void }>()",
+                    "emits: ['change', 'update', 'delete'],\n",
+                ),
+                (
+                    "type EventName = 'a' | 'b'
+                    defineEmits<(e: EventName) => void>()",
+                    "emits: ['a', 'b'],\n",
+                ),
+                (
+                    "defineEmits<{ change: [value: number]; 'update-value': [] }>()",
+                    "emits: ['change', 'update-value'],\n",
+                ),
+            ];
+
+            for (script, expected) in cases {
+                let input = format!("<script setup lang=\"ts\">\n{}\n</script>", script);
+                assert_eq!(compile_props_and_emits(&input), expected, "{}", script);
+            }
+        }
+
+        #[test]
+        fn type_based_props_from_normal_script() {
+            let input = "<script lang=\"ts\">
+export interface Props { title: string }
+export default {}
+</script>
+<script setup lang=\"ts\">
+defineProps<Props>()
+</script>";
+            assert_eq!(
+                compile_props_and_emits(input),
+                "props: { title: { type: String, required: true } },\n"
+            );
+        }
+
+        #[test]
+        fn type_based_props_errors() {
+            let cases = vec![
+                (
+                    "defineProps<{ a: string }>({ a: String })",
+                    "defineProps() cannot accept both type and non-type arguments at the same time",
+                ),
+                (
+                    "defineProps<Unknown>()",
+                    "type argument passed to defineProps() must be a literal type, or a reference to an interface or literal type",
+                ),
+                ("defineProps<{ a: }>()", "unexpected '}' in type"),
+            ];
+
+            for (script, expected) in cases {
+                let input = format!("<script setup lang=\"ts\">{}</script>", script);
+                let result = Parser::new_and_parse(&input, "example").unwrap();
+                let err = js::compile_script_setup(&result, result.script_setup.as_ref().unwrap())
+                    .unwrap_err();
+                assert_eq!(err.message, expected, "{}", script);
+            }
+        }
+
+        #[test]
+        fn typescript_imports_only_returned_when_used_in_template() {
+            let output = compile_setup(
+                "<template><my-component :a=\"Type\" /></template>
+<script setup lang=\"ts\">
+import MyComponent from './MyComponent.vue'
+import { Type, OtherType } from './types'
+</script>",
+            );
+            assert!(output.contains("return { MyComponent, Type }"));
+        }
+
+        #[test]
+        fn template_uses_setup_bindings() {
+            // The template is placed before the script to make sure it's compiled with knowledge of the setup bindings
+            let output = render(
+                "<template><div><my-component :a=\"count\" :b=\"other\" @click=\"count++\" /><Foo /></div></template>
+<script setup>
+import MyComponent from './MyComponent.vue'
+import Foo from './Foo.vue'
+const count = ref(0)
+</script>",
+            );
+
+            assert!(output.contains("const _setup = _vm._self._setupProxy;"));
+            assert!(output.contains(
+                "_c(_setup.MyComponent,{props:{\"a\":_setup.count,\"b\":_vm.other},on:{\"click\":function($event){_setup.count++}}})"
+            ));
+            assert!(output.contains("_c(_setup.Foo)"));
+        }
+
+        #[test]
+        fn errors() {
+            let cases = vec![
+                (
+                    "<script setup>export const a = 1</script>",
+                    "<script setup> cannot contain ES module exports",
+                ),
+                (
+                    "<script setup>defineProps(); defineProps()</script>",
+                    "duplicate defineProps() call",
+                ),
+                ("<script setup>const a = )</script>", "unexpected ')'"),
+                (
+                    "<script setup>const data = await fetch('a')</script>",
+                    "top level await is not supported in <script setup> as setup() can't be async",
+                ),
+                (
+                    "<script setup>if (a) { await b() }</script>",
+                    "top level await is not supported in <script setup> as setup() can't be async",
+                ),
+                (
+                    "<script setup>const f = async () => 1\nawait f()</script>",
+                    "top level await is not supported in <script setup> as setup() can't be async",
+                ),
+                (
+                    "<script setup>withDefaults(defineProps({ a: String }), { a: 'a' })</script>",
+                    "withDefaults() can only be used with a type-based defineProps() declaration",
+                ),
+                (
+                    "<script setup lang=\"ts\">withDefaults(defineProps<{ a?: string }>(), defaults)</script>",
+                    "the defaults passed to withDefaults() must be an object literal",
+                ),
+                (
+                    "<script setup lang=\"ts\">withDefaults(defineProps<{ a?: string }>(), { ...defaults })</script>",
+                    "the defaults passed to withDefaults() can only contain properties with static keys",
+                ),
+                (
+                    "<script lang=\"ts\"></script><script setup></script>",
+                    "<script> and <script setup> must have the same lang",
+                ),
+                (
+                    "<script setup></script><script setup></script>",
+                    "can't have multiple <script setup> in your code",
+                ),
+            ];
+
+            for (input, expected) in cases {
+                let result = Parser::new_and_parse(input, "example");
+                assert_eq!(result.unwrap_err().message, expected, "{}", input);
+            }
+        }
     }
 }
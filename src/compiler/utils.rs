@@ -19,3 +19,100 @@ pub fn write_str_escaped(input: &str, quote: char, escape_char: char, dest: &mut
         dest.push(c);
     }
 }
+
+// Decodes the html entities of an attribute value: &quot;a&quot; -> "a"
+// Same as the decodeAttr of vue this supports &lt; &gt; &quot; &amp; and numeric entities (&#39; &#x27;)
+// Unknown or invalid entities are left as is
+pub fn decode_attr_entities(value: &str) -> String {
+    let mut resp = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        resp.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let decoded = match &rest[1..end] {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "amp" => Some('&'),
+                name => {
+                    let code = match name.strip_prefix('#') {
+                        Some(hex) if hex.starts_with(['x', 'X']) => {
+                            u32::from_str_radix(&hex[1..], 16).ok()
+                        }
+                        Some(decimal) => decimal.parse().ok(),
+                        None => None,
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            decoded.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                resp.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                resp.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    resp.push_str(rest);
+    resp
+}
+
+// Returns the PascalCase component name of a vue file, /src/components/user-card.vue -> UserCard
+// Returns None if the file name can't be turned into a valid component name
+pub fn component_name_from_id(id: &str) -> Option<String> {
+    let path = id.split('?').next().unwrap_or(id);
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let name = file_name.strip_suffix(".vue").unwrap_or(file_name);
+
+    let mut resp = String::with_capacity(name.len());
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            resp.push(first.to_ascii_uppercase());
+            resp.extend(chars);
+        }
+    }
+
+    match resp.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => Some(resp),
+        _ => None,
+    }
+}
+
+// Hashes the input using 64 bit FNV-1a, returns the hash as 16 hex characters
+// Used for the scope id of components, all 64 bits are kept as components with the same scope id share their scoped styles
+// This is not a cryptographic hash
+pub fn hash(input: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in input.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// Returns the scope id of a component, the hash of the path relative to the root so the id is the same on every machine
+// In production the content is also hashed so the styles of different versions of a component never conflict
+pub fn scope_id(id: &str, content: &str, root: Option<&str>, production: bool) -> String {
+    let path = id.replace('\\', "/");
+    let root = root.map(|root| root.replace('\\', "/"));
+    let relative_path = root
+        .as_ref()
+        .and_then(|root| path.strip_prefix(root.trim_end_matches('/')))
+        .and_then(|relative| relative.strip_prefix('/'))
+        .unwrap_or(&path);
+
+    if production {
+        hash(&format!("{}\n{}", relative_path, content))
+    } else {
+        hash(relative_path)
+    }
+}
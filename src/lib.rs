@@ -1,9 +1,9 @@
 mod compiler;
 mod utils;
 
-use compiler::template::to_js::template_to_js;
-use compiler::utils::{write_str, write_str_escaped};
-use compiler::{error::ParserError, style, Parser, SourceLocation, Style};
+use compiler::template::to_js::{css_vars_to_js, template_to_js};
+use compiler::utils::{component_name_from_id, scope_id, write_str, write_str_escaped};
+use compiler::{error::ParserError, js, style, CompilerOptions, Parser, SourceLocation, Style};
 use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
@@ -16,21 +16,30 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 struct ComponentCache {
     logic: Option<String>,
     styles: Vec<String>,
+    custom_blocks: Vec<String>,
 }
 
 #[wasm_bindgen]
 pub struct Plugin {
     components_cache: HashMap<String, ComponentCache>,
+    options: CompilerOptions,
+    // Called with (src, importer) to read the file referenced by <template src="..">
+    read_file: Option<js_sys::Function>,
 }
 
 #[wasm_bindgen]
 impl Plugin {
+    // options is an optional object with the compiler options, Example:
+    // new Plugin({ delimiters: ['${', '}'], comments: true, allowedGlobals: ['Symbol'] })
+    // The root (the project root) and production options are used for the scope id of components:
+    // new Plugin({ root: config.root, production: config.isProduction })
+    // The readFile option is a function that receives the src of a <template src=".."> and the id of the
+    // vue file, it should return the content of the referenced file as a string
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Self {
-        utils::set_panic_hook();
-        Self {
-            components_cache: HashMap::new(),
-        }
+    pub fn new(options: JsValue) -> Result<Plugin, JsValue> {
+        let mut plugin = Self::new_with_options(parse_js_options(&options)?);
+        plugin.read_file = parse_js_read_file(&options)?;
+        Ok(plugin)
     }
 
     #[wasm_bindgen]
@@ -43,6 +52,8 @@ impl Plugin {
         match ParsedId::parse(id) {
             ParsedId::Other => None,
             ParsedId::Main => None,
+            // The content of <style src=".."> is loaded from the referenced file
+            ParsedId::Style(style) if style.src => None,
             ParsedId::Style(style) => {
                 if let Some(component) = self.components_cache.get(style.id) {
                     if let Some(style) = component.styles.get(style.index as usize) {
@@ -59,37 +70,51 @@ impl Plugin {
                 }
                 Some(String::new())
             }
+            ParsedId::Custom(block) => {
+                if let Some(component) = self.components_cache.get(block.id) {
+                    if let Some(block) = component.custom_blocks.get(block.index as usize) {
+                        return Some(block.clone());
+                    }
+                }
+                Some(String::new())
+            }
         }
     }
 
+    // Returns the transformed code or None if the id is not handled by this plugin
+    // Throws an object that can be passed to this.error of a rollup / vite plugin if the code contains errors:
+    // { message, id, loc: { line, column }, frame }
     #[wasm_bindgen]
-    pub fn transform(&mut self, code: &str, id: &str) -> Option<String> {
+    pub fn transform(&mut self, code: &str, id: &str) -> Result<Option<String>, JsValue> {
+        self.transform_code(code, id)
+            .map_err(|err| parser_error_to_js(&err, id))
+    }
+
+    fn transform_code(&mut self, code: &str, id: &str) -> Result<Option<String>, ParserError> {
         match ParsedId::parse(id) {
-            ParsedId::Other => None,
+            ParsedId::Other => Ok(None),
             ParsedId::Main => {
                 let mut resp: Vec<char> = Vec::new();
-                // TODO remove unwrap
-                self.transform_main(code, id, &mut resp).unwrap();
-                Some(resp.iter().collect())
+                self.transform_main(code, id, &mut resp)?;
+                Ok(Some(resp.iter().collect()))
             }
             ParsedId::Style(style_data) => {
-                if style_data.scoped {
+                if let Some(scope_id) = style_data.scoped {
                     let mut parser = Parser::new(code);
-                    // TODO remove unwrap
                     let injection_points =
-                        style::parse_scoped_css(&mut parser, style::SelectorsEnd::EOF).unwrap();
+                        style::parse_scoped_css(&mut parser, style::SelectorsEnd::EOF)?;
 
-                    Some(style::gen_scoped_css(
+                    Ok(Some(style::gen_scoped_css(
                         &mut parser,
                         SourceLocation(0, code.len()),
                         injection_points,
-                        &simple_hash_crypto_unsafe(id),
-                    ))
+                        scope_id,
+                    )))
                 } else {
-                    None
+                    Ok(None)
                 }
             }
-            ParsedId::Logic(_) => None,
+            ParsedId::Logic(_) | ParsedId::Custom(_) => Ok(None),
         }
     }
 
@@ -99,87 +124,266 @@ impl Plugin {
         id: &str,
         resp: &mut Vec<char>,
     ) -> Result<(), ParserError> {
-        let id_hash = &simple_hash_crypto_unsafe(id);
-        let parsed_code = Parser::new_and_parse(code, id_hash)?;
+        let id_hash = &scope_id(
+            id,
+            code,
+            self.options.root.as_deref(),
+            self.options.production,
+        );
+        let mut parsed_code =
+            Parser::new_and_parse_with_options(code, id_hash, self.options.clone())?;
+
+        // <template src=".."> is compiled after parsing so the bindings of <script setup> are known
+        let template_src = parsed_code
+            .template
+            .as_ref()
+            .and_then(|template| Some((template.src.clone()?, template.source.clone())));
+        if let Some((src, location)) = template_src {
+            let content = self
+                .read_template_src(&src, id)
+                .map_err(|message| ParserError::new_at(&parsed_code, &location, message))?;
+            parsed_code.compile_template_src(&content)?;
+        }
 
         let script = parsed_code.script.as_ref();
+        let script_setup = parsed_code.script_setup.as_ref();
         let template = parsed_code.template.as_ref();
         let styles = &parsed_code.styles;
 
         let mut cache_entry = ComponentCache {
             logic: None,
             styles: Vec::new(),
+            custom_blocks: Vec::new(),
         };
 
         if styles.len() != 0 {
             for (index, style_kind) in styles.iter().enumerate() {
+                let style = match style_kind {
+                    Style::Normal(style) => style,
+                    Style::DirectScopedCSS(style) => {
+                        cache_entry
+                            .styles
+                            .push(style::rewrite_v_binds(style, id_hash));
+
+                        // Writes:
+                        // import 'id.vue?vue&type=style&index=0&pre-scoped=true&lang.css';
+                        write_str("import '", resp);
+                        write_str_escaped(id, '\'', '\\', resp);
+                        write_str("?vue&type=style&index=", resp);
+                        write_str(&index.to_string(), resp);
+                        write_str("&pre-scoped=true&lang.css';\n", resp);
+                        continue;
+                    }
+                };
+
                 // Writes:
-                // id.vue?vue&type=style&index=0&lang.css
+                // import 'id.vue?vue&type=style&index=0&lang.css';
                 // Or
-                // id.vue?vue&type=style&index=0&scoped=true&lang.css
-                write_str("import '", resp);
-                write_str_escaped(id, '\'', '\\', resp);
-                write_str("?vue&type=style&index=", resp);
-                write_str(&index.to_string(), resp);
+                // import 'id.vue?vue&type=style&index=0&scoped=0a1b2c3d4e5f6a7b&lang.css';
+                // Or for css modules
+                // import style0 from 'id.vue?vue&type=style&index=0&module=true&lang.module.css';
+                // Or for styles imported from another file
+                // import './style.css';
+                // import './style.css?vue&type=style&index=0&src=true&scoped=0a1b2c3d4e5f6a7b&lang.css';
+                write_str("import ", resp);
+                if style.module.is_some() {
+                    write_str("style", resp);
+                    write_str(&index.to_string(), resp);
+                    write_str(" from ", resp);
+                }
+                resp.push('\'');
 
-                match style_kind {
-                    Style::Normal(style) => {
-                        cache_entry.styles.push(style.content.string(&parsed_code));
+                if let Some(src) = style.src.as_ref() {
+                    cache_entry.styles.push(String::new());
 
-                        if style.scoped {
-                            write_str("&scoped=true", resp);
-                        }
-
-                        let lang_extension =
-                            style.lang.as_ref().map(|v| v.as_str()).unwrap_or("css");
-                        write_str("&lang.", resp);
-                        write_str(&lang_extension, resp);
+                    write_str_escaped(src, '\'', '\\', resp);
+                    if !style.scoped && style.module.is_none() {
+                        write_str("';\n", resp);
+                        continue;
                     }
-                    Style::DirectScopedCSS(style) => {
-                        cache_entry.styles.push(style.clone());
+                    resp.push(if src.contains('?') { '&' } else { '?' });
+                } else {
+                    cache_entry.styles.push(style::rewrite_v_binds(
+                        &style.content.string(&parsed_code),
+                        id_hash,
+                    ));
 
-                        write_str("&pre-scoped=true", resp);
-                        write_str("&lang.css", resp);
-                    }
-                };
+                    write_str_escaped(id, '\'', '\\', resp);
+                    resp.push('?');
+                }
+
+                write_str("vue&type=style&index=", resp);
+                write_str(&index.to_string(), resp);
+                if style.src.is_some() {
+                    write_str("&src=true", resp);
+                }
+                if style.scoped {
+                    write_str("&scoped=", resp);
+                    write_str(id_hash, resp);
+                }
 
+                // Vite only handles ids ending with .module.css (or .module.scss, ..) as css modules
+                if style.module.is_some() {
+                    write_str("&module=true&lang.module.", resp);
+                } else {
+                    write_str("&lang.", resp);
+                }
+                write_str(style.lang.as_deref().unwrap_or("css"), resp);
                 write_str("';\n", resp);
             }
         }
 
-        if script.is_none() && template.is_none() {
+        for (index, block) in parsed_code.custom_blocks.iter().enumerate() {
+            cache_entry
+                .custom_blocks
+                .push(block.content.string(&parsed_code));
+
+            // Writes:
+            // import block0 from 'id.vue?vue&type=custom&index=0&blockType=i18n&lang.json';
+            write_str("import block", resp);
+            write_str(&index.to_string(), resp);
+            write_str(" from '", resp);
+            write_str_escaped(id, '\'', '\\', resp);
+            write_str("?vue&type=custom&index=", resp);
+            write_str(&index.to_string(), resp);
+            write_str("&blockType=", resp);
+            write_str(&encode_query_component(&block.type_), resp);
+            for (key, value) in block.attrs.iter() {
+                // Skip the keys used by the plugin itself
+                if matches!(
+                    key.as_str(),
+                    "vue" | "type" | "index" | "blockType" | "lang"
+                ) {
+                    continue;
+                }
+                resp.push('&');
+                write_str(&encode_query_component(key), resp);
+                if let Some(value) = value {
+                    resp.push('=');
+                    write_str(&encode_query_component(value), resp);
+                }
+            }
+            if let Some(lang) = block.lang.as_ref() {
+                write_str("&lang.", resp);
+                write_str(&encode_query_component(lang), resp);
+            }
+            write_str("';\n", resp);
+        }
+
+        if script.is_none() && script_setup.is_none() && template.is_none() {
             write_str("export default undefined;", resp);
+            self.components_cache.insert(id.to_string(), cache_entry);
             return Ok(());
         }
 
-        if let Some(script) = script {
-            cache_entry.logic = Some(script.content.string(&parsed_code));
+        let inline_logic = if self.options.inline_script {
+            js::compile_inline_script(&parsed_code, is_main_module_name)?
+        } else {
+            None
+        };
+
+        // <script setup> is compiled together with the normal script into one module
+        let logic = match (script_setup, script) {
+            // The script is placed within this module, see below
+            _ if inline_logic.is_some() => None,
+            (Some(script_setup), _) => Some((
+                js::compile_script_setup(&parsed_code, script_setup)?,
+                script_setup.lang.as_ref(),
+            )),
+            // <script src=".."> is imported directly, see below
+            (None, Some(script)) if script.src.is_some() => None,
+            (None, Some(script)) => {
+                Some((script.content.string(&parsed_code), script.lang.as_ref()))
+            }
+            (None, None) => None,
+        };
+        let script_src = script
+            .and_then(|script| script.src.as_ref())
+            .filter(|_| script_setup.is_none());
+
+        if let Some(inline_logic) = inline_logic {
+            resp.push('\n');
+            write_str(&inline_logic, resp);
+        } else if let Some((logic, lang)) = logic {
+            cache_entry.logic = Some(logic);
 
             // Writes:
             // id.vue?vue&type=logic&lang.js
             write_str("\nimport * as logic from '", resp);
             write_str_escaped(id, '\'', '\\', resp);
-            let lang_extension = script.lang.as_ref().map(|v| v.as_str()).unwrap_or("js");
+            let lang_extension = lang.map(|v| v.as_str()).unwrap_or("js");
             write_str("?vue&type=logic&lang.", resp);
             write_str(lang_extension, resp);
             write_str("';\nconst c = logic.default || {};", resp);
+        } else if let Some(src) = script_src {
+            // Writes:
+            // ./script.js
+            write_str("\nimport * as logic from '", resp);
+            write_str_escaped(src, '\'', '\\', resp);
+            write_str("';\nconst c = logic.default || {};", resp);
         } else {
             write_str("\nconst c = {};", resp);
         }
 
         // Write the renderer to the result
-        template_to_js(&parsed_code, resp);
+        template_to_js(&parsed_code, resp)?;
+        css_vars_to_js(&parsed_code, id_hash, resp)?;
+
+        // Write the _scopeId to the result, only components with scoped styles need it
+        let has_scoped_style = styles.iter().any(|style| match style {
+            Style::Normal(style) => style.scoped,
+            Style::DirectScopedCSS(_) => true,
+        });
+        if has_scoped_style {
+            write_str("\nc._scopeId = 'data-v-", resp);
+            write_str(id_hash, resp);
+            resp.push('\'');
+        }
 
-        // Write the _scopeId to the result
-        write_str("\nc._scopeId = 'data-v-", resp);
-        write_str(id_hash, resp);
-        resp.push('\'');
+        // Name the component after the file if the script didn't name it
+        if self.options.infer_component_name {
+            if let Some(name) = component_name_from_id(id) {
+                write_str("\nif (!c.name) c.name = '", resp);
+                write_str(&name, resp);
+                write_str("';", resp);
+            }
+        }
 
         // Write the filename to the component
         write_str("\nc.__file = '", resp);
         write_str_escaped(id, '\'', '\\', resp);
         resp.push('\'');
 
+        // Inject the class maps of the css modules, <style module> is available as this.$style
+        let modules: Vec<(usize, &String)> = styles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, style)| match style {
+                Style::Normal(style) => Some((index, style.module.as_ref()?)),
+                Style::DirectScopedCSS(_) => None,
+            })
+            .collect();
+        if !modules.is_empty() {
+            write_str("\nc.beforeCreate = [function() {", resp);
+            for (index, name) in modules {
+                write_str("\n    this[\"", resp);
+                write_str_escaped(name, '"', '\\', resp);
+                write_str("\"] = style", resp);
+                write_str(&index.to_string(), resp);
+                resp.push(';');
+            }
+            write_str("\n}].concat(c.beforeCreate || []);", resp);
+        }
+
+        // Custom blocks that export a function can modify the component
+        for index in 0..parsed_code.custom_blocks.len() {
+            write_str("\nif (typeof block", resp);
+            write_str(&index.to_string(), resp);
+            write_str(" === 'function') block", resp);
+            write_str(&index.to_string(), resp);
+            write_str("(c);", resp);
+        }
+
         write_str("\nexport default c;", resp);
 
         self.components_cache.insert(id.to_string(), cache_entry);
@@ -188,42 +392,188 @@ impl Plugin {
     }
 }
 
-pub fn simple_hash_crypto_unsafe(input: &str) -> String {
-    let mut hash: [u8; 8] = [0; 8];
+impl Plugin {
+    pub fn new_with_options(options: CompilerOptions) -> Self {
+        utils::set_panic_hook();
+        Self {
+            components_cache: HashMap::new(),
+            options,
+            read_file: None,
+        }
+    }
 
-    for (index, b) in input.as_bytes().iter().enumerate() {
-        let index_in_hash = index % 8;
-        hash[index_in_hash] ^= b;
+    // Reads the file referenced by <template src=".."> using the readFile option
+    fn read_template_src(&self, src: &str, importer: &str) -> Result<String, String> {
+        let read_file = self.read_file.as_ref().ok_or_else(|| {
+            format!(
+                "<template src=\"{}\"> requires the readFile option to be set",
+                src
+            )
+        })?;
+
+        read_file
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from_str(src),
+                &JsValue::from_str(importer),
+            )
+            .ok()
+            .and_then(|content| content.as_string())
+            .ok_or_else(|| format!("unable to read template src \"{}\"", src))
     }
+}
 
-    let hex_chars = [
-        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
-    ];
-    let mut resp = String::with_capacity(16);
-    for b in hash {
-        resp.push(hex_chars[(b >> 4) as usize]);
-        resp.push(hex_chars[(b & 0x0F) as usize]);
+// Converts a parser error into an object that can be passed to this.error of a rollup / vite plugin
+fn parser_error_to_js(err: &ParserError, id: &str) -> JsValue {
+    let (line, column) = err.line_column();
+
+    let loc = js_sys::Object::new();
+    let set = |target: &js_sys::Object, key: &str, value: JsValue| {
+        // Setting a property of a plain object never fails
+        let _ = js_sys::Reflect::set(target, &JsValue::from_str(key), &value);
+    };
+    set(&loc, "line", JsValue::from_f64(line as f64));
+    set(&loc, "column", JsValue::from_f64(column as f64));
+
+    let resp = js_sys::Object::new();
+    set(&resp, "message", JsValue::from_str(&err.to_string()));
+    set(&resp, "id", JsValue::from_str(id));
+    set(&resp, "loc", loc.into());
+    set(&resp, "frame", JsValue::from_str(&err.code_frame()));
+    resp.into()
+}
+
+fn parse_js_read_file(options: &JsValue) -> Result<Option<js_sys::Function>, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(None);
     }
-    resp
+
+    let read_file = js_sys::Reflect::get(options, &JsValue::from_str("readFile"))?;
+    if read_file.is_undefined() {
+        return Ok(None);
+    }
+    if !read_file.is_function() {
+        return Err(JsValue::from_str("readFile option must be a function"));
+    }
+    Ok(Some(js_sys::Function::from(read_file)))
+}
+
+fn parse_js_options(options: &JsValue) -> Result<CompilerOptions, JsValue> {
+    let mut resp = CompilerOptions::default();
+    if options.is_undefined() || options.is_null() {
+        return Ok(resp);
+    }
+
+    let delimiters = js_sys::Reflect::get(options, &JsValue::from_str("delimiters"))?;
+    if !delimiters.is_undefined() {
+        let invalid_delimiters_err =
+            || JsValue::from_str("delimiters option must be an array with 2 non empty strings");
+
+        if !js_sys::Array::is_array(&delimiters) {
+            return Err(invalid_delimiters_err());
+        }
+        let delimiters = js_sys::Array::from(&delimiters);
+        if delimiters.length() != 2 {
+            return Err(invalid_delimiters_err());
+        }
+        match (delimiters.get(0).as_string(), delimiters.get(1).as_string()) {
+            (Some(open), Some(close)) if !open.is_empty() && !close.is_empty() => {
+                resp.delimiters = (open, close);
+            }
+            _ => return Err(invalid_delimiters_err()),
+        }
+    }
+
+    let comments = js_sys::Reflect::get(options, &JsValue::from_str("comments"))?;
+    if !comments.is_undefined() {
+        resp.comments = comments
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str("comments option must be a boolean"))?;
+    }
+
+    let lower_optional_chaining =
+        js_sys::Reflect::get(options, &JsValue::from_str("lowerOptionalChaining"))?;
+    if !lower_optional_chaining.is_undefined() {
+        resp.lower_optional_chaining = lower_optional_chaining
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str("lowerOptionalChaining option must be a boolean"))?;
+    }
+
+    let inline_script = js_sys::Reflect::get(options, &JsValue::from_str("inlineScript"))?;
+    if !inline_script.is_undefined() {
+        resp.inline_script = inline_script
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str("inlineScript option must be a boolean"))?;
+    }
+
+    let infer_component_name =
+        js_sys::Reflect::get(options, &JsValue::from_str("inferComponentName"))?;
+    if !infer_component_name.is_undefined() {
+        resp.infer_component_name = infer_component_name
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str("inferComponentName option must be a boolean"))?;
+    }
+
+    let root = js_sys::Reflect::get(options, &JsValue::from_str("root"))?;
+    if !root.is_undefined() {
+        resp.root = Some(
+            root.as_string()
+                .ok_or_else(|| JsValue::from_str("root option must be a string"))?,
+        );
+    }
+
+    let production = js_sys::Reflect::get(options, &JsValue::from_str("production"))?;
+    if !production.is_undefined() {
+        resp.production = production
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str("production option must be a boolean"))?;
+    }
+
+    let allowed_globals = js_sys::Reflect::get(options, &JsValue::from_str("allowedGlobals"))?;
+    if !allowed_globals.is_undefined() {
+        let invalid_allowed_globals_err =
+            || JsValue::from_str("allowedGlobals option must be an array of strings");
+
+        if !js_sys::Array::is_array(&allowed_globals) {
+            return Err(invalid_allowed_globals_err());
+        }
+        for global in js_sys::Array::from(&allowed_globals).iter() {
+            let global = global.as_string().ok_or_else(invalid_allowed_globals_err)?;
+            resp.allowed_globals.push(global);
+        }
+    }
+
+    Ok(resp)
 }
 
 enum ParsedId<'a> {
-    Other,                  // Not a vue file
-    Main,                   // The global vue file
-    Style(TargetStyle<'a>), // Targets a style within a vue file
-    Logic(&'a str),         // Targets a script tag within a vue file
+    Other,                   // Not a vue file
+    Main,                    // The global vue file
+    Style(TargetStyle<'a>),  // Targets a style within a vue file
+    Logic(&'a str),          // Targets a script tag within a vue file
+    Custom(TargetBlock<'a>), // Targets a custom block (<i18n>, <docs>, ..) within a vue file
+}
+
+struct TargetBlock<'a> {
+    id: &'a str,
+    index: u16,
 }
 
 struct TargetStyle<'a> {
     id: &'a str,
     index: u16,
-    scoped: bool,
+    // The scope id of the component if the style is scoped
+    scoped: Option<&'a str>,
+    // The style is imported from another file using <style src="..">
+    src: bool,
 }
 
 impl<'a> ParsedId<'a> {
     fn parse(id: &'a str) -> Self {
         if let Some((first, args)) = id.split_once('?') {
-            if !first.ends_with(".vue") {
+            // Styles imported using <style src=".." scoped> keep the id of the imported file
+            let is_vue_file = first.ends_with(".vue");
+            if !is_vue_file && args.split('&').next() != Some("vue") {
                 return Self::Other;
             }
 
@@ -231,7 +581,8 @@ impl<'a> ParsedId<'a> {
             // vue&type=style&index=0&lang.css
             let mut import_type = ImportType::Main;
             let mut index = 0u16;
-            let mut scoped = false;
+            let mut scoped = None;
+            let mut src = false;
 
             for elem in args.split('&') {
                 if let Some((key, value)) = elem.split_once('=') {
@@ -240,6 +591,7 @@ impl<'a> ParsedId<'a> {
                             import_type = match value {
                                 "style" => ImportType::Style,
                                 "logic" => ImportType::Logic,
+                                "custom" => ImportType::Custom,
                                 _ => ImportType::Main,
                             };
                         }
@@ -249,7 +601,10 @@ impl<'a> ParsedId<'a> {
                             }
                         }
                         "scoped" => {
-                            scoped = true;
+                            scoped = Some(value);
+                        }
+                        "src" => {
+                            src = true;
                         }
                         _ => {}
                     }
@@ -257,13 +612,16 @@ impl<'a> ParsedId<'a> {
             }
 
             match import_type {
-                ImportType::Main => ParsedId::Main,
                 ImportType::Style => ParsedId::Style(TargetStyle {
                     id: first,
                     index,
                     scoped,
+                    src,
                 }),
+                _ if !is_vue_file => ParsedId::Other,
+                ImportType::Main => ParsedId::Main,
                 ImportType::Logic => ParsedId::Logic(first),
+                ImportType::Custom => ParsedId::Custom(TargetBlock { id: first, index }),
             }
         } else if id.ends_with(".vue") {
             Self::Main
@@ -277,4 +635,37 @@ enum ImportType {
     Main,
     Style,
     Logic,
+    Custom,
+}
+
+// Returns true if the name is declared by the main module of a component,
+// a script using one of these names can't be placed within the main module
+fn is_main_module_name(name: &str) -> bool {
+    if matches!(
+        name,
+        "c" | "logic" | "__default__" | "_cssVars" | "_setCssVars"
+    ) {
+        return true;
+    }
+    // The imports of the css modules and custom blocks: style0, block0, ..
+    ["style", "block"]
+        .iter()
+        .any(|prefix| match name.strip_prefix(prefix) {
+            Some(index) => !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()),
+            None => false,
+        })
+}
+
+// Percent encodes a value so it can be used as key or value within the query of an import
+fn encode_query_component(value: &str) -> String {
+    let mut resp = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                resp.push(b as char)
+            }
+            _ => resp.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    resp
 }
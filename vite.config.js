@@ -47,7 +47,13 @@ const newVue2Plugin = () => {
         // transforms the code into the module
         async transform(code, id) {
             // const t1 = performance.now()
-            const transformedCode = plugin.transform(code, id)
+            let transformedCode
+            try {
+                transformedCode = plugin.transform(code, id)
+            } catch (err) {
+                // err is { message, id, loc: { line, column }, frame }
+                this.error(err)
+            }
             if (transformedCode) {
                 return { code: transformedCode, map: null }
             }